@@ -0,0 +1,52 @@
+//! When the `embedded` feature is enabled, this bundles a tree of compiled
+//! zone files into a blob for the library to include. See the `embedded`
+//! module for the environment variables that control it.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[path = "src/tree.rs"]
+mod tree;
+
+#[allow(dead_code)]
+#[path = "src/embedded/bundle.rs"]
+mod bundle;
+
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBEDDED").is_none() {
+        return;
+    }
+
+    println!("cargo:rerun-if-env-changed=ZONEINFO_COMPILED_EMBED_DIR");
+    println!("cargo:rerun-if-env-changed=ZONEINFO_COMPILED_EMBED_ZONES");
+
+    let root = env::var_os("ZONEINFO_COMPILED_EMBED_DIR")
+                   .map(PathBuf::from)
+                   .unwrap_or_else(|| PathBuf::from("/usr/share/zoneinfo"));
+
+    let out_path = Path::new(&env::var_os("OUT_DIR").unwrap()).join("zoneinfo.bin");
+
+    // Without a tree to bundle, every lookup would fail at runtime, so the
+    // build fails instead (on Windows, say, where there’s no default tree).
+    println!("cargo:rerun-if-changed={}", root.display());
+    let mut bundle = match bundle::Bundle::from_zoneinfo(&root) {
+        Ok(bundle) if !bundle.zone_names().is_empty() => bundle,
+        Ok(_) => {
+            panic!("No zones found in zoneinfo tree {}; set ZONEINFO_COMPILED_EMBED_DIR to a tree of compiled zone files", root.display());
+        },
+        Err(e) => {
+            panic!("Could not read zoneinfo tree {}: {}; set ZONEINFO_COMPILED_EMBED_DIR to a tree of compiled zone files", root.display(), e);
+        },
+    };
+
+    if let Ok(selection) = env::var("ZONEINFO_COMPILED_EMBED_ZONES") {
+        let selection: Vec<&str> = selection.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+
+        bundle.select(|name| selection.iter().any(|s| {
+            if s.ends_with('/') { name.starts_with(s) } else { name == *s }
+        }));
+    }
+
+    bundle.write_to(&out_path).expect("Failed to write zoneinfo bundle");
+}
@@ -0,0 +1,109 @@
+extern crate zoneinfo_compiled;
+
+use std::env;
+use std::process;
+
+use zoneinfo_compiled::diff::{self, Change};
+use zoneinfo_compiled::zi::days_from_civil;
+
+
+/// 1900-01-01 to 2100-01-01, unless other years are given.
+const DEFAULT_YEARS: (i64, i64) = (1900, 2100);
+
+/// The furthest from year 0 that a year can be given, which keeps the
+/// timestamps in range.
+const MAX_YEAR: i64 = 1_000_000;
+
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 && args.len() != 4 {
+        usage();
+    }
+
+    let (first_year, last_year) = if args.len() == 4 {
+        match (args[2].parse(), args[3].parse()) {
+            (Ok(first_year), Ok(last_year)) if -MAX_YEAR <= first_year && first_year <= last_year && last_year <= MAX_YEAR => {
+                (first_year, last_year)
+            },
+            _ => usage(),
+        }
+    }
+    else {
+        DEFAULT_YEARS
+    };
+
+    let range = year_start(first_year) .. year_start(last_year + 1);
+    let tree_diff = match diff::diff_trees(&args[0], &args[1], range) {
+        Ok(tree_diff) => tree_diff,
+        Err(e) => {
+            println!("Error: {}", e);
+            process::exit(1);
+        },
+    };
+
+    for name in &tree_diff.removed {
+        println!("- {}", name);
+    }
+
+    for name in &tree_diff.added {
+        println!("+ {}", name);
+    }
+
+    for (name, changes) in &tree_diff.changed {
+        println!("~ {}", name);
+        for change in changes {
+            println!("    {}", describe(change));
+        }
+    }
+
+    for (name, error) in &tree_diff.failed {
+        println!("! {}: {}", name, error);
+    }
+
+    if tree_diff != diff::TreeDiff::default() {
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: tzdiff OLD_ZONEINFO NEW_ZONEINFO [FIRST_YEAR LAST_YEAR]");
+    process::exit(2);
+}
+
+/// Returns the Unix timestamp of the start of a year.
+fn year_start(year: i64) -> i64 {
+    days_from_civil(year, 1, 1) * 86400
+}
+
+fn describe(change: &Change) -> String {
+    match *change {
+        Change::Initial { ref old, ref new } => {
+            format!("initially {} ({}) instead of {} ({})", new.name, new.offset, old.name, old.offset)
+        },
+        Change::TransitionAdded { timestamp, ref local_time_type } => {
+            format!("{}: new transition to {} ({})", timestamp, local_time_type.name, local_time_type.offset)
+        },
+        Change::TransitionRemoved { timestamp, ref local_time_type } => {
+            format!("{}: no more transition to {} ({})", timestamp, local_time_type.name, local_time_type.offset)
+        },
+        Change::TransitionMoved { old_timestamp, new_timestamp, ref local_time_type } => {
+            format!("{}: transition to {} ({}) moved to {}", old_timestamp, local_time_type.name, local_time_type.offset, new_timestamp)
+        },
+        Change::TransitionChanged { timestamp, ref old, ref new } => {
+            format!("{}: transition to {} ({}) instead of {} ({})", timestamp, new.name, new.offset, old.name, old.offset)
+        },
+        Change::Footer { ref old, ref new } => {
+            format!("footer {:?} instead of {:?}", new, old)
+        },
+        Change::LeapSecondAdded(ls) => {
+            format!("{}: new leap second ({})", ls.timestamp, ls.leap_second_count)
+        },
+        Change::LeapSecondRemoved(ls) => {
+            format!("{}: no more leap second ({})", ls.timestamp, ls.leap_second_count)
+        },
+        Change::LeapSecondChanged { old, new } => {
+            format!("{}: leap second correction {} instead of {}", new.timestamp, new.leap_second_count, old.leap_second_count)
+        },
+    }
+}
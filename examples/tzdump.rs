@@ -6,18 +6,14 @@ use std::io::Read;
 use std::path::Path;
 
 
-// This example is broken until we have a way to get at the transitions in
-// time zone data directly. Right now it just does a Rust Debug dump of the
-// file...
-
 fn main() {
     for arg in env::args().skip(1) {
-        match File::open(&Path::new(&arg)) {
+        match File::open(Path::new(&arg)) {
             Ok(mut file) => {
                 let mut contents = Vec::new();
                 file.read_to_end(&mut contents).unwrap();
                 match zoneinfo_compiled::parse(contents) {
-                    Ok(tzdata) => println!("{:#?}", tzdata),
+                    Ok(tzdata) => tzdump(tzdata),
                     Err(e)     => println!("Error: {}", e),
                 }
             },
@@ -26,12 +22,18 @@ fn main() {
     }
 }
 
-// fn tzdump(mut tz: zoneinfo_compiled::TZData) {
-//     tz.transitions.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-//
-//     for t in tz.transitions {
-//         let l = &*t.local_time_type;
-//         println!("{:11?}: name:{:5} offset:{:5} DST:{:5} type:{:?}",
-//                   t.timestamp, l.name, l.offset, l.is_dst, l.transition_type);
-//     }
-// }
+fn tzdump(tz: zoneinfo_compiled::TZData) {
+    let l = &tz.local_time_types[0];
+    println!("{:>11}: name:{:5} offset:{:5} DST:{:5} std:{:?} ut:{:?}",
+              "initial", l.name, l.offset, l.is_dst, l.indicators.is_std, l.indicators.is_ut);
+
+    for t in &tz.transitions {
+        let l = &tz.local_time_types[t.local_time_type];
+        println!("{:11?}: name:{:5} offset:{:5} DST:{:5} std:{:?} ut:{:?}",
+                  t.timestamp, l.name, l.offset, l.is_dst, l.indicators.is_std, l.indicators.is_ut);
+    }
+
+    if let Some(footer) = tz.footer {
+        println!("{:>11}: {}", "footer", footer);
+    }
+}
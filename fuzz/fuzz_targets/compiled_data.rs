@@ -0,0 +1,9 @@
+#![no_main]
+
+use datetime::zone::TimeZone;
+use libfuzzer_sys::fuzz_target;
+use zoneinfo_compiled::CompiledData;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = TimeZone::parse(data.to_vec());
+});
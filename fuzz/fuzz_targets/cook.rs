@@ -0,0 +1,38 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zoneinfo_compiled::parser::{self, Limits};
+
+/// 2000 to 2040, which is short enough to extrapolate a footer over.
+const RANGE: std::ops::Range<i64> = 946_684_800 .. 2_208_988_800;
+
+fuzz_target!(|data: &[u8]| {
+    let raw = match parser::parse(data.to_vec(), Limits::sensible()) {
+        Ok(raw) => raw,
+        Err(_)  => return,
+    };
+
+    let tz = match zoneinfo_compiled::cook(raw) {
+        Ok(tz)  => tz,
+        Err(_)  => return,
+    };
+
+    for &timestamp in &[ i64::MIN, -1, 0, 1_000_000_000, i64::MAX ] {
+        tz.local_time_type_at(timestamp);
+        tz.resolve_local(timestamp);
+    }
+
+    for t in &tz.transitions {
+        tz.resolve_local(t.timestamp);
+    }
+
+    let _ = tz.equivalent(&tz, RANGE);
+
+    let mut normalized = tz.clone();
+    normalized.normalize();
+
+    let mut truncated = tz.clone();
+    let _ = truncated.truncate(RANGE.start, RANGE.end);
+
+    let _ = zoneinfo_compiled::writer::to_bytes(&tz);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zoneinfo_compiled::parser::{self, Limits};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut tz) = parser::parse(data.to_vec(), Limits::sensible()) {
+        let _ = tz.compact();
+        let _ = zoneinfo_compiled::writer::write(&tz);
+    }
+});
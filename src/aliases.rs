@@ -0,0 +1,354 @@
+//! Resolving zone aliases and canonical names
+//!
+//! Many zones in a zoneinfo tree are just other names for the same data:
+//! `US/Eastern` is `America/New_York`, and `Zulu` is `Etc/UTC`. The `Link`
+//! lines in the database itself say which names these are, so if the tree
+//! contains them -- in `tzdata.zi` or `backward` -- they get used to group
+//! the names together, and to decide which name in each group is the
+//! canonical one. Otherwise, this module falls back to the symbolic links
+//! and hard links that zic installs for them.
+//!
+//! Some trees are copied file by file, as in many container images, which
+//! turns every link into a separate copy. `AliasIndex::with_copies` also
+//! groups together the files that have the same contents, to recover the
+//! aliases from such a tree. This is opt-in, as two zones that are really
+//! different can still compile to the same bytes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Result, TZData};
+use tab::ZoneMetadata;
+use tree;
+use zi;
+
+
+/// The canonical name of a zone, along with every other name for it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Aliases {
+
+    /// The canonical name of the zone, such as "America/New_York".
+    pub canonical: String,
+
+    /// The other names for the zone, such as "US/Eastern", in order.
+    pub aliases: Vec<String>,
+}
+
+
+/// An index of which zones in a zoneinfo tree are aliases of one another.
+#[derive(Debug, Clone)]
+pub struct AliasIndex {
+
+    /// The root of the zoneinfo tree.
+    root: PathBuf,
+
+    /// Each group of names that refer to the same zone.
+    groups: Vec<Aliases>,
+
+    /// The index into `groups` of the group each name belongs to.
+    names: BTreeMap<String, usize>,
+}
+
+impl AliasIndex {
+
+    /// Indexes the zoneinfo tree under the given root, such as
+    /// `/usr/share/zoneinfo`.
+    ///
+    /// This reads every zone file in the tree, so it’s best to create one
+    /// index and use it for every lookup.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<AliasIndex> {
+        AliasIndex::index(root.as_ref(), false)
+    }
+
+    /// Indexes the zoneinfo tree under the given root, also treating files
+    /// with the same contents as aliases of one another.
+    ///
+    /// This finds the aliases in a tree whose links were turned into copies,
+    /// but it can also group two zones that happen to compile to the same
+    /// data, even though the database says they’re different.
+    pub fn with_copies<P: AsRef<Path>>(root: P) -> Result<AliasIndex> {
+        AliasIndex::index(root.as_ref(), true)
+    }
+
+    /// Indexes the tree, grouping copies together only if `copies` is set.
+    fn index(root: &Path, copies: bool) -> Result<AliasIndex> {
+        let root = root.to_path_buf();
+        let names = tree::zone_names(&root)?;
+        let mut groups = Groups::new(names.len());
+
+        let positions: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (&**n, i)).collect();
+
+        // Symbolic links point straight at the file they’re an alias of...
+        let canonical_root = fs::canonicalize(&root)?;
+        for (i, name) in names.iter().enumerate() {
+            let path = root.join(name);
+            if !fs::symlink_metadata(&path)?.file_type().is_symlink() {
+                continue;
+            }
+
+            let target = fs::canonicalize(&path)?;
+            let target_name = target.strip_prefix(&canonical_root).ok()
+                                    .and_then(|t| t.to_str())
+                                    .map(|t| t.replace('\\', "/"));
+
+            if let Some(&j) = target_name.and_then(|t| positions.get(&*t)) {
+                groups.join(i, j);
+            }
+        }
+
+        // ...while hard links share an inode, and copies share their contents.
+        let mut by_inode: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut by_contents: HashMap<Vec<u8>, usize> = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            let path = root.join(name);
+
+            if let Some(inode) = inode(&path)? {
+                let j = *by_inode.entry(inode).or_insert(i);
+                groups.join(i, j);
+            }
+
+            if copies {
+                let j = *by_contents.entry(fs::read(&path)?).or_insert(i);
+                groups.join(i, j);
+            }
+        }
+
+        // The database’s own links are the most reliable source, if any are
+        // available.
+        let zi_data = link_lines(&root);
+        if let Some(ref zi_data) = zi_data {
+            for (link, target) in &zi_data.links {
+                let target = zi_data.resolve(target).unwrap_or(target);
+                if let (Some(&i), Some(&j)) = (positions.get(&**link), positions.get(target)) {
+                    groups.join(i, j);
+                }
+            }
+        }
+
+        let metadata = ZoneMetadata::from_zoneinfo(&root).ok();
+
+        // Pick the canonical name of each group, preferring a name that the
+        // database says is a zone, then one from the zone table, then one
+        // that isn’t a symbolic link, before falling back to the first name.
+        let rank = |name: &str| -> Result<u8> {
+            let is_zone = zi_data.as_ref().map_or(false, |z| z.zones.contains_key(name));
+            let in_table = metadata.as_ref().map_or(false, |m| m.zone(name).is_some());
+            let is_symlink = fs::symlink_metadata(root.join(name))?.file_type().is_symlink();
+            Ok(if is_zone { 0 } else if in_table { 1 } else if !is_symlink { 2 } else { 3 })
+        };
+
+        let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for i in 0 .. names.len() {
+            members.entry(groups.find(i)).or_default().push(i);
+        }
+
+        let mut index = AliasIndex { root: root.clone(), groups: Vec::new(), names: BTreeMap::new() };
+        for group in members.values() {
+            let mut best = group[0];
+            let mut best_rank = rank(&names[best])?;
+            for &i in &group[1 ..] {
+                let r = rank(&names[i])?;
+                if r < best_rank {
+                    best = i;
+                    best_rank = r;
+                }
+            }
+
+            for &i in group {
+                index.names.insert(names[i].clone(), index.groups.len());
+            }
+
+            index.groups.push(Aliases {
+                canonical: names[best].clone(),
+                aliases: group.iter().filter(|&&i| i != best).map(|&i| names[i].clone()).collect(),
+            });
+        }
+
+        Ok(index)
+    }
+
+    /// Returns the canonical name and aliases of the zone with the given
+    /// name, or `None` if there is no such zone in the tree.
+    pub fn resolve(&self, name: &str) -> Option<&Aliases> {
+        self.names.get(name).map(|&i| &self.groups[i])
+    }
+
+    /// Returns the canonical name of the zone with the given name, or `None`
+    /// if there is no such zone in the tree.
+    pub fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.resolve(name).map(|a| &*a.canonical)
+    }
+
+    /// Returns every group of aliases in the tree.
+    pub fn groups(&self) -> &[Aliases] {
+        &self.groups
+    }
+
+    /// Reads the zone with the given name from the tree, naming it after the
+    /// zone’s canonical name.
+    pub fn load(&self, name: &str) -> Result<TZData> {
+        let canonical = self.canonical_name(name)
+                            .ok_or_else(|| zi::Error::UnknownZone(name.to_owned()))?;
+
+        super::parse_named(fs::read(self.root.join(canonical))?, canonical)
+    }
+}
+
+
+/// Returns the canonical name and aliases of the zone with the given name
+/// in the zoneinfo tree under the given root, or `None` if there is no such
+/// zone.
+///
+/// This indexes the whole tree every time it’s called; use an `AliasIndex`
+/// to look up more than one zone.
+pub fn resolve<P: AsRef<Path>>(root: P, name: &str) -> Result<Option<Aliases>> {
+    let index = AliasIndex::new(root)?;
+    Ok(index.resolve(name).cloned())
+}
+
+
+/// Reads the `Link` lines from the tree’s copy of the database source, if
+/// it has one that can be parsed. A source file that can’t be parsed gets
+/// ignored, leaving the tree’s own links as the only ones to go on.
+fn link_lines(root: &Path) -> Option<zi::ZiData> {
+    [ "tzdata.zi", "backward" ].iter()
+        .map(|file_name| root.join(file_name))
+        .find(|path| path.is_file())
+        .and_then(|path| zi::ZiData::from_file(path).ok())
+}
+
+/// Returns the device and inode numbers of the file at the given path.
+#[cfg(unix)]
+fn inode(path: &Path) -> Result<Option<(u64, u64)>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path)?;
+    Ok(Some((metadata.dev(), metadata.ino())))
+}
+
+#[cfg(not(unix))]
+fn inode(_path: &Path) -> Result<Option<(u64, u64)>> {
+    Ok(None)
+}
+
+
+/// A union-find structure, for grouping names together.
+struct Groups {
+    parents: Vec<usize>,
+}
+
+impl Groups {
+    fn new(count: usize) -> Groups {
+        Groups { parents: (0 .. count).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+
+        i
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::{empty_tree, EST};
+
+    /// Creates a zoneinfo tree where America/Panama and EST are copies of
+    /// the same file, and Etc/Zulu is a hard link to Etc/UTC.
+    fn tree(test: &str) -> PathBuf {
+        let root = empty_tree("aliases", test);
+        fs::create_dir_all(root.join("America")).unwrap();
+        fs::create_dir_all(root.join("US")).unwrap();
+        fs::create_dir_all(root.join("Etc")).unwrap();
+
+        let mut other = EST.to_vec();
+        other[44] = 0xFE;
+
+        fs::write(root.join("America/Panama"), EST).unwrap();
+        fs::write(root.join("EST"), EST).unwrap();
+        fs::write(root.join("Etc/UTC"), &other).unwrap();
+        fs::hard_link(root.join("Etc/UTC"), root.join("Etc/Zulu")).unwrap();
+        fs::write(root.join("zone.tab"), "PA\t+0858-07932\tAmerica/Panama\n").unwrap();
+        fs::write(root.join("iso3166.tab"), "PA\tPanama\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn hard_links_and_copies() {
+        let root = tree("hard-links-and-copies");
+        let index = AliasIndex::new(&root).unwrap();
+
+        // EST and America/Panama have the same contents, but nothing says
+        // that they’re the same zone.
+        assert_eq!(index.resolve("EST"), Some(&Aliases {
+            canonical: "EST".to_owned(),
+            aliases: vec![],
+        }));
+
+        assert_eq!(index.canonical_name("Etc/Zulu"), Some("Etc/UTC"));
+        assert_eq!(index.groups().len(), 3);
+        assert_eq!(index.load("EST").unwrap().name, Some("EST".to_owned()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn copies() {
+        let root = tree("copies");
+        let index = AliasIndex::with_copies(&root).unwrap();
+
+        assert_eq!(index.resolve("EST"), Some(&Aliases {
+            canonical: "America/Panama".to_owned(),
+            aliases: vec![ "EST".to_owned() ],
+        }));
+
+        assert_eq!(index.canonical_name("Etc/Zulu"), Some("Etc/UTC"));
+        assert_eq!(index.groups().len(), 2);
+        assert_eq!(index.load("EST").unwrap().name, Some("America/Panama".to_owned()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn link_lines() {
+        let root = tree("link-lines");
+        fs::write(root.join("tzdata.zi"), "Z EST -5 - EST\nL EST America/Panama\n").unwrap();
+
+        let aliases = resolve(&root, "America/Panama").unwrap().unwrap();
+        assert_eq!(aliases.canonical, "EST");
+        assert_eq!(aliases.aliases, vec![ "America/Panama".to_owned() ]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn invalid_link_lines() {
+        let root = tree("invalid-link-lines");
+        fs::write(root.join("tzdata.zi"), "Z EST -5 - EST\nL EST\n").unwrap();
+
+        let index = AliasIndex::new(&root).unwrap();
+        assert_eq!(index.canonical_name("America/Panama"), Some("America/Panama"));
+        assert_eq!(index.canonical_name("Etc/Zulu"), Some("Etc/UTC"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let root = tree("symlinks");
+        ::std::os::unix::fs::symlink("../America/Panama", root.join("US/Panama")).unwrap();
+
+        let index = AliasIndex::new(&root).unwrap();
+        assert_eq!(index.canonical_name("US/Panama"), Some("America/Panama"));
+        assert_eq!(index.resolve("Mars/Olympus_Mons"), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
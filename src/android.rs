@@ -0,0 +1,266 @@
+//! Reading Android's bundled `tzdata` archive
+//!
+//! Rather than a tree of zoneinfo files, Android ships every compiled zone
+//! in a single file called `tzdata`, usually found at
+//! `/apex/com.android.tzdata/etc/tz/tzdata` or
+//! `/system/usr/share/zoneinfo/tzdata`. This file has:
+//!
+//! - a header, containing the database version (such as `tzdata2023c`)
+//!   followed by the offsets of the other two sections;
+//! - an index of every zone, each entry holding a 40-byte name, and the
+//!   position and length of its data;
+//! - a data area, containing each zone's compiled file, one after another.
+//!
+//! This module reads the header and index, and hands each zone's data to
+//! the parser in the same way as a file read from a zoneinfo tree.
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::result;
+
+use super::{Result, TZData};
+use parser;
+
+
+/// The number of bytes taken up by the header: the version, then the
+/// offsets of the index, the data, and a final section that isn't used.
+const HEADER_LENGTH: usize = 12 + 4 + 4 + 4;
+
+/// The number of bytes taken up by a zone's name in the index.
+const NAME_LENGTH: usize = 40;
+
+/// The number of bytes taken up by each index entry: the name, then the
+/// start, length, and a raw offset value that is no longer used.
+const ENTRY_LENGTH: usize = NAME_LENGTH + 4 + 4 + 4;
+
+
+/// The contents of an Android `tzdata` file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AndroidTzData {
+
+    /// The version of the time zone database, such as "2023c".
+    version: String,
+
+    /// The name of each zone in the index, along with the range of bytes
+    /// in the buffer that make up its compiled data.
+    entries: Vec<(String, Range<usize>)>,
+
+    /// The entire contents of the file.
+    buf: Vec<u8>,
+}
+
+impl AndroidTzData {
+
+    /// Reads and indexes a `tzdata` file from the filesystem.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<AndroidTzData> {
+        use std::io::BufReader;
+        use std::fs::File;
+
+        let f = File::open(path)?;
+        let mut r = BufReader::new(f);
+        let mut contents: Vec<u8> = Vec::new();
+
+        r.read_to_end(&mut contents)?;
+        parse(contents)
+    }
+
+    /// Returns the version of the time zone database, such as "2023c".
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the names of every zone in the index, in the order they
+    /// appear in the file.
+    pub fn zone_names(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| &*e.0).collect()
+    }
+
+    /// Returns the compiled data for the zone with the given name, or `None`
+    /// if there is no such zone.
+    pub fn zone_data(&self, name: &str) -> Option<&[u8]> {
+        self.entries.iter()
+                    .find(|e| e.0 == name)
+                    .map(|e| &self.buf[e.1.clone()])
+    }
+
+    /// Parses the compiled data for the zone with the given name into its
+    /// internal structure, returning an error if there is no such zone.
+    pub fn parse_zone(&self, name: &str, limits: parser::Limits) -> Result<parser::TZData> {
+        let data = self.zone_data(name)
+                       .ok_or_else(|| Error::UnknownZone(name.to_owned()))?;
+
+        parser::parse(data.to_vec(), limits)
+    }
+
+    /// Parses and interprets the compiled data for the zone with the given
+    /// name, returning an error if there is no such zone.
+    pub fn tz_data(&self, name: &str) -> Result<TZData> {
+        let tz = self.parse_zone(name, parser::Limits::sensible())?;
+        let mut data = super::cook(tz)?;
+        data.set_name(name);
+        Ok(data)
+    }
+}
+
+
+/// Reads the header and index of a `tzdata` file, returning an error if
+/// either is invalid or points outside of the buffer.
+pub fn parse(buf: Vec<u8>) -> Result<AndroidTzData> {
+    let mut cursor = Cursor::new(&buf[..]);
+
+    let mut magic = [0u8; 12];
+    cursor.read_exact(&mut magic)?;
+    let version = header_version(&magic).ok_or(Error::InvalidHeader)?;
+
+    let index_offset = cursor.read_i32::<BigEndian>()?;
+    let data_offset  = cursor.read_i32::<BigEndian>()?;
+    let _final_offset = cursor.read_i32::<BigEndian>()?;
+
+    if index_offset < HEADER_LENGTH as i32 || data_offset < index_offset || data_offset as usize > buf.len()
+    || (data_offset - index_offset) as usize % ENTRY_LENGTH != 0 {
+        return Err(Box::new(Error::InvalidIndex));
+    }
+
+    let (index_offset, data_offset) = (index_offset as usize, data_offset as usize);
+    let count = (data_offset - index_offset) / ENTRY_LENGTH;
+
+    cursor.set_position(index_offset as u64);
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0 .. count {
+        let mut name = [0u8; NAME_LENGTH];
+        cursor.read_exact(&mut name)?;
+
+        let start   = cursor.read_i32::<BigEndian>()?;
+        let length  = cursor.read_i32::<BigEndian>()?;
+        let _unused = cursor.read_i32::<BigEndian>()?;
+
+        // Names are padded with null bytes when they’re shorter than the
+        // space available for them.
+        let name_bytes = name.iter().cloned().take_while(|&c| c != 0).collect();
+
+        if start < 0 || length < 0 {
+            return Err(Box::new(Error::InvalidIndex));
+        }
+
+        let range = data_offset.checked_add(start as usize)
+            .and_then(|start| Some(start .. start.checked_add(length as usize)?))
+            .filter(|range| range.end <= buf.len())
+            .ok_or(Error::InvalidIndex)?;
+
+        entries.push((String::from_utf8(name_bytes)?, range));
+    }
+
+    Ok(AndroidTzData { version, entries, buf })
+}
+
+/// Returns the database version from the first twelve bytes of a `tzdata`
+/// file, which are `tzdata` followed by the version and a null byte.
+pub(crate) fn header_version(magic: &[u8; 12]) -> Option<String> {
+    if !magic.starts_with(b"tzdata") || magic[11] != 0 {
+        return None;
+    }
+
+    String::from_utf8(magic[6 .. 11].to_vec()).ok()
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Error {
+
+    /// The error when the file doesn’t start with a `tzdata` version.
+    InvalidHeader,
+
+    /// The error when the index overlaps the header, or it or one of its
+    /// entries refers to data outside of the file.
+    InvalidIndex,
+
+    /// The error when there is no zone with the requested name.
+    UnknownZone(String),
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidHeader   => "invalid header",
+            Error::InvalidIndex    => "invalid index",
+            Error::UnknownZone(_)  => "unknown zone",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidHeader          => write!(f, "invalid tzdata header"),
+            Error::InvalidIndex           => write!(f, "invalid tzdata index"),
+            Error::UnknownZone(ref name)  => write!(f, "unknown zone {:?}", name),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::EST;
+
+    fn archive() -> Vec<u8> {
+        let mut buf = b"tzdata2023c\0".to_vec();
+        buf.extend_from_slice(&[ 0, 0, 0, 24,  0, 0, 0, 128,  0, 0, 0, 128 + 112 ]);
+
+        for (i, name) in [ "EST", "Etc/GMT+5" ].iter().enumerate() {
+            let mut entry = [0u8; ENTRY_LENGTH];
+            entry[.. name.len()].copy_from_slice(name.as_bytes());
+            entry[43] = (i * EST.len()) as u8;
+            entry[47] = EST.len() as u8;
+            buf.extend_from_slice(&entry);
+        }
+
+        buf.extend_from_slice(EST);
+        buf.extend_from_slice(EST);
+        buf
+    }
+
+    #[test]
+    fn index() {
+        let tzdata = parse(archive()).unwrap();
+        assert_eq!(tzdata.version(), "2023c");
+        assert_eq!(tzdata.zone_names(), vec![ "EST", "Etc/GMT+5" ]);
+        assert_eq!(tzdata.zone_data("Etc/GMT+5"), Some(EST));
+        assert_eq!(tzdata.zone_data("Etc/GMT+6"), None);
+    }
+
+    #[test]
+    fn zone() {
+        let tzdata = parse(archive()).unwrap();
+        let data = tzdata.tz_data("EST").unwrap();
+        assert_eq!(data.local_time_types[0].offset, -18000);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut buf = archive();
+        buf.truncate(200);
+        assert!(parse(buf).is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut buf = archive();
+        buf[24 + 40 .. 24 + 44].copy_from_slice(&i32::MAX.to_be_bytes());
+        assert!(parse(buf).is_err());
+
+        let mut buf = archive();
+        buf[24 + 44 .. 24 + 48].copy_from_slice(&i32::MAX.to_be_bytes());
+        assert!(parse(buf).is_err());
+
+        let mut buf = archive();
+        buf[12 .. 20].copy_from_slice(&[ 0, 0, 0, 0,  0, 0, 0, 104 ]);
+        assert!(parse(buf).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Building zone data in code
+//!
+//! This module has a builder for creating zones without reading a file,
+//! for test fixtures and for synthetic zones. Local time types are added
+//! first, then the transitions between them, along with any leap seconds
+//! and a footer; the data only gets checked when it’s built.
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::result;
+
+use {FooterCache, Indicators, LeapSecond, LocalTimeType, Result, TZData, Transition, MAX_OFFSET};
+use {parser, posix, writer};
+
+
+/// A builder for zone data.
+///
+/// The first local time type added is the one in effect before the first
+/// transition, and `add_type` returns the index that transitions use to
+/// refer to each one.
+///
+/// ```
+/// use zoneinfo_compiled::TZDataBuilder;
+///
+/// let mut builder = TZDataBuilder::new();
+/// let gmt = builder.add_type("GMT", 0, false);
+/// let bst = builder.add_type("BST", 3600, true);
+/// builder.add_transition(1_711_846_800, bst)
+///        .add_transition(1_729_990_800, gmt)
+///        .footer("GMT0BST,M3.5.0/1,M10.5.0");
+///
+/// let tz = builder.build().unwrap();
+/// assert_eq!(tz.local_time_type_at(1_720_000_000).name, "BST");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TZDataBuilder {
+    name: Option<String>,
+    local_time_types: Vec<LocalTimeType>,
+    transitions: Vec<Transition>,
+    leap_seconds: Vec<LeapSecond>,
+    footer: Option<String>,
+}
+
+impl TZDataBuilder {
+
+    /// Creates a builder with no local time types or transitions.
+    pub fn new() -> TZDataBuilder {
+        TZDataBuilder::default()
+    }
+
+    /// Sets the name of the zone, such as "Europe/Paris".
+    pub fn name(&mut self, name: &str) -> &mut TZDataBuilder {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Adds a local time type with the given abbreviation, offset from UTC
+    /// in seconds, and DST flag, returning its index.
+    pub fn add_type(&mut self, name: &str, offset: i64, is_dst: bool) -> usize {
+        self.add_type_with_indicators(name, offset, is_dst, Indicators::UNSPECIFIED)
+    }
+
+    /// Adds a local time type that also has standard/wall and UT/local
+    /// indicators, returning its index.
+    pub fn add_type_with_indicators(&mut self, name: &str, offset: i64, is_dst: bool, indicators: Indicators) -> usize {
+        self.local_time_types.push(LocalTimeType { name: name.to_owned(), offset, is_dst, indicators });
+        self.local_time_types.len() - 1
+    }
+
+    /// Adds a transition to the local time type with the given index at the
+    /// given Unix timestamp. Transitions have to be added in order.
+    pub fn add_transition(&mut self, at: i64, local_time_type: usize) -> &mut TZDataBuilder {
+        self.transitions.push(Transition { timestamp: at, local_time_type });
+        self
+    }
+
+    /// Adds a leap second at the given Unix timestamp, where the given
+    /// correction is the total number of leap seconds from then on. Leap
+    /// seconds have to be added in order.
+    pub fn leap_second(&mut self, at: i64, correction: i32) -> &mut TZDataBuilder {
+        self.leap_seconds.push(LeapSecond { timestamp: at, leap_second_count: correction });
+        self
+    }
+
+    /// Sets the POSIX TZ string describing the rules in effect after the
+    /// last transition, such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+    pub fn footer(&mut self, posix: &str) -> &mut TZDataBuilder {
+        self.footer = Some(posix.to_owned());
+        self
+    }
+
+    /// Checks the data and returns it as interpreted zone data.
+    pub fn build(&self) -> Result<TZData> {
+        self.validate()?;
+
+        Ok(TZData {
+            name: self.name.clone(),
+            local_time_types: self.local_time_types.clone(),
+            transitions: self.transitions.clone(),
+            leap_seconds: self.leap_seconds.clone(),
+            footer: self.footer.clone(),
+            footer_rule: FooterCache::default(),
+        })
+    }
+
+    /// Checks the data and returns it as the internal structure of a
+    /// version 2 zoneinfo file, ready to be written out.
+    pub fn build_raw(&self) -> Result<parser::TZData> {
+        writer::uncook(&self.build()?)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.local_time_types.is_empty() {
+            return Err(Box::new(Error::NoLocalTimeTypes));
+        }
+
+        for ltt in &self.local_time_types {
+            if ltt.name.is_empty() || ltt.name.contains('\0') {
+                return Err(Box::new(Error::InvalidAbbreviation));
+            }
+
+            if ltt.offset.unsigned_abs() > MAX_OFFSET as u64 {
+                return Err(Box::new(Error::OffsetOutOfRange { offset: ltt.offset }));
+            }
+        }
+
+        for (i, t) in self.transitions.iter().enumerate() {
+            if t.local_time_type >= self.local_time_types.len() {
+                return Err(Box::new(Error::InvalidLocalTimeTypeIndex { index: t.local_time_type }));
+            }
+
+            if i > 0 && self.transitions[i - 1].timestamp >= t.timestamp {
+                return Err(Box::new(Error::UnsortedTransitions { timestamp: t.timestamp }));
+            }
+        }
+
+        if self.leap_seconds.windows(2).any(|w| w[0].timestamp >= w[1].timestamp) {
+            return Err(Box::new(Error::UnsortedLeapSeconds));
+        }
+
+        if let Some(ref footer) = self.footer {
+            posix::parse(footer)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// An error that occurs when building zone data that doesn’t make sense.
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when no local time types were added, so there’s no way to
+    /// know the local time at all.
+    NoLocalTimeTypes,
+
+    /// The error when a local time type’s abbreviation is empty or contains
+    /// a null character.
+    InvalidAbbreviation,
+
+    /// The error when a local time type’s offset is further from UTC than
+    /// any zone’s can be.
+    OffsetOutOfRange {
+
+        /// The offset of the local time type, in seconds.
+        offset: i64,
+    },
+
+    /// The error when a transition refers to a local time type that wasn’t
+    /// added.
+    InvalidLocalTimeTypeIndex {
+
+        /// The index that the transition refers to.
+        index: usize,
+    },
+
+    /// The error when a transition isn’t later than the one before it.
+    UnsortedTransitions {
+
+        /// The timestamp of the transition that’s out of order.
+        timestamp: i64,
+    },
+
+    /// The error when leap seconds aren’t in ascending order of time.
+    UnsortedLeapSeconds,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NoLocalTimeTypes                  => "no local time types",
+            Error::InvalidAbbreviation               => "invalid abbreviation",
+            Error::OffsetOutOfRange { .. }           => "offset out of range",
+            Error::InvalidLocalTimeTypeIndex { .. }  => "invalid local time type index",
+            Error::UnsortedTransitions { .. }        => "unsorted transitions",
+            Error::UnsortedLeapSeconds               => "unsorted leap seconds",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::NoLocalTimeTypes => write!(f, "no local time types were added"),
+
+            Error::InvalidAbbreviation => write!(f, "abbreviation is empty or contains a null character"),
+
+            Error::OffsetOutOfRange { offset } => {
+                write!(f, "offset of {} seconds is too far from UTC", offset)
+            },
+
+            Error::InvalidLocalTimeTypeIndex { index } => {
+                write!(f, "transition refers to local time type {}, which doesn’t exist", index)
+            },
+
+            Error::UnsortedTransitions { timestamp } => {
+                write!(f, "transition at {} is out of order", timestamp)
+            },
+
+            Error::UnsortedLeapSeconds => write!(f, "leap seconds are out of order"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn london() -> TZDataBuilder {
+        let mut builder = TZDataBuilder::new();
+        let gmt = builder.add_type("GMT", 0, false);
+        let bst = builder.add_type("BST", 3600, true);
+        builder.name("Europe/London")
+               .add_transition(1_711_846_800, bst)
+               .add_transition(1_729_990_800, gmt)
+               .leap_second(1_483_228_826, 27)
+               .footer("GMT0BST,M3.5.0/1,M10.5.0");
+        builder
+    }
+
+    #[test]
+    fn cooked_and_raw() {
+        let builder = london();
+        let tz = builder.build().unwrap();
+        assert_eq!(tz.name, Some("Europe/London".to_owned()));
+        assert_eq!(tz.transitions[0], Transition { timestamp: 1_711_846_800, local_time_type: 1 });
+        assert_eq!(tz.local_time_type_at(1_720_000_000).name, "BST");
+
+        let raw = builder.build_raw().unwrap();
+        assert_eq!(raw.header.num_transitions, 2);
+        assert_eq!(raw.strings, b"GMT\0BST\0".to_vec());
+
+        let mut cooked = ::cook(raw).unwrap();
+        cooked.set_name("Europe/London");
+        assert_eq!(cooked, tz);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(TZDataBuilder::new().build().is_err());
+
+        let mut builder = london();
+        builder.add_transition(1_729_990_800, 0);
+        assert!(builder.build().is_err());
+
+        let mut builder = london();
+        builder.add_transition(1_800_000_000, 2);
+        assert!(builder.build().is_err());
+
+        let mut builder = london();
+        builder.leap_second(1_435_708_825, 26);
+        assert!(builder.build().is_err());
+
+        let mut builder = london();
+        builder.add_type("", 0, false);
+        assert!(builder.build().is_err());
+
+        let mut builder = london();
+        builder.add_type("LMT", 100_000, false);
+        assert!(builder.build().is_err());
+
+        let mut builder = london();
+        builder.add_type("LMT", i64::MIN, false);
+        assert!(builder.build().is_err());
+        assert!(TZData::fixed(i64::MIN, "X").is_err());
+
+        let mut builder = london();
+        builder.footer("GMT0BST,M3.5.0/1");
+        assert!(builder.build_raw().is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! Caching zones from a zoneinfo tree
+//!
+//! Reading and parsing a zone on every lookup is wasteful, but a program
+//! that runs for a long time still needs to notice when the tzdata package
+//! gets updated underneath it. `ZoneCache` keeps each zone it has read,
+//! along with enough about its file to tell whether it has changed since,
+//! and re-reads the ones that have whenever it gets refreshed -- either by
+//! calling `refresh`, or, with the `watch` feature, as soon as anything in
+//! the tree changes.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+#[cfg(feature = "datetime")] use datetime::zone::TimeZone;
+
+use {parse_named, zoneinfo_path, Result, TZData};
+use version::{self, Version};
+
+
+/// A cache of the zones in a zoneinfo tree, keyed by name, such as
+/// “Europe/Paris”.
+///
+/// Zones are read the first time they’re asked for, and shared after that.
+/// Refreshing the cache checks each zone’s file, and reads it again if its
+/// modification time, inode, or size has changed *and* its contents have
+/// too, so a package manager rewriting every file with the same data
+/// doesn’t replace anything.
+#[derive(Debug)]
+pub struct ZoneCache {
+    root: PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    zones: HashMap<String, Entry>,
+    version: Option<Version>,
+}
+
+/// A zone that has been read, and what its file looked like at the time.
+#[derive(Debug, Clone)]
+struct Entry {
+    data: Arc<TZData>,
+    stamp: Stamp,
+    hash: u64,
+}
+
+/// The parts of a file’s metadata that change when it gets replaced.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    inode: u64,
+    len: u64,
+}
+
+impl ZoneCache {
+
+    /// Creates an empty cache of the zones in the zoneinfo tree under the
+    /// given root, such as `/usr/share/zoneinfo`.
+    pub fn new<R: AsRef<Path>>(root: R) -> ZoneCache {
+        let root = root.as_ref().to_path_buf();
+        let version = version::read(&root).ok();
+
+        ZoneCache {
+            root,
+            state: Mutex::new(State { zones: HashMap::new(), version }),
+        }
+    }
+
+    /// Returns the root of the zoneinfo tree that this cache reads from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the version of the time zone database that the tree was
+    /// compiled from, as read by `version::read` when the cache was created
+    /// or last refreshed, if it says.
+    pub fn version(&self) -> Option<Version> {
+        self.lock().version.clone()
+    }
+
+    /// Returns the zone with the given name, reading it from the tree if
+    /// it hasn’t been read yet.
+    pub fn get(&self, name: &str) -> Result<Arc<TZData>> {
+        if let Some(entry) = self.lock().zones.get(name) {
+            return Ok(Arc::clone(&entry.data));
+        }
+
+        let entry = self.load(name)?;
+        let mut state = self.lock();
+        let entry = state.zones.entry(name.to_owned()).or_insert(entry);
+        Ok(Arc::clone(&entry.data))
+    }
+
+    /// Returns the zone with the given name as a `datetime` time zone,
+    /// reading it from the tree if it hasn’t been read yet.
+    #[cfg(feature = "datetime")]
+    pub fn time_zone(&self, name: &str) -> Result<TimeZone> {
+        Ok(::compat::datetime::runtime_time_zone(&*self.get(name)?))
+    }
+
+    /// Returns the names of the zones that have been read, in order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.lock().zones.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Checks every zone that has been read against its file, reading the
+    /// ones whose contents have changed again, and forgetting the ones
+    /// whose files have gone. The version gets read again too.
+    ///
+    /// Returns the names of the zones that changed or went, in order. Any
+    /// `Arc` that has already been handed out keeps the old data.
+    ///
+    /// A zone whose file is still there but can’t be read or parsed, such
+    /// as one that a package manager is halfway through writing, keeps its
+    /// old data, and gets checked again on the next refresh. So does a zone
+    /// whose file can’t be looked at for any reason other than it not
+    /// existing.
+    pub fn refresh(&self) -> Result<Vec<String>> {
+        let entries: Vec<(String, Entry)> = self.lock().zones.iter()
+                                                .map(|(name, entry)| (name.clone(), entry.clone()))
+                                                .collect();
+
+        let mut changed = Vec::new();
+        for (name, old) in entries {
+            let path = zoneinfo_path(&self.root, &name)?;
+            let stamp = match fs::metadata(&path) {
+                Ok(metadata) => stamp(&metadata),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                    self.lock().zones.remove(&name);
+                    changed.push(name);
+                    continue;
+                },
+                Err(_) => continue,
+            };
+
+            if stamp == old.stamp {
+                continue;
+            }
+
+            let new = match self.load(&name) {
+                Ok(new) => new,
+                Err(_)  => continue,
+            };
+
+            let is_changed = new.hash != old.hash;
+            let new = if is_changed { new } else { Entry { data: old.data, .. new } };
+            self.lock().zones.insert(name.clone(), new);
+
+            if is_changed {
+                changed.push(name);
+            }
+        }
+
+        let version = version::read(&self.root).ok();
+        self.lock().version = version;
+
+        changed.sort();
+        Ok(changed)
+    }
+
+    /// Forgets every zone that has been read.
+    pub fn clear(&self) {
+        self.lock().zones.clear();
+    }
+
+    /// Reads and parses the zone with the given name.
+    fn load(&self, name: &str) -> Result<Entry> {
+        let path = zoneinfo_path(&self.root, name)?;
+        let stamp = stamp(&fs::metadata(&path)?);
+        let contents = fs::read(&path)?;
+
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let data = Arc::new(parse_named(contents, name)?);
+        Ok(Entry { data, stamp, hash })
+    }
+
+    /// Locks the state, carrying on if another thread panicked while
+    /// holding the lock, as the state is only ever replaced whole.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(unix)]
+fn stamp(metadata: &fs::Metadata) -> Stamp {
+    use std::os::unix::fs::MetadataExt;
+    Stamp { modified: metadata.modified().ok(), inode: metadata.ino(), len: metadata.len() }
+}
+
+#[cfg(not(unix))]
+fn stamp(metadata: &fs::Metadata) -> Stamp {
+    Stamp { modified: metadata.modified().ok(), inode: 0, len: metadata.len() }
+}
+
+
+#[cfg(all(feature = "watch", target_os = "linux"))]
+pub use self::watch::Watcher;
+
+#[cfg(all(feature = "watch", target_os = "linux"))]
+mod watch {
+    use std::io::{self, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Weak};
+    use std::thread::{self, JoinHandle};
+
+    use inotify::{Inotify, WatchMask};
+    use libc;
+
+    use super::ZoneCache;
+    use Result;
+
+
+    /// A thread that refreshes a cache whenever anything in its zoneinfo
+    /// tree changes, using inotify. It stops when it gets dropped, or when
+    /// the cache does.
+    ///
+    /// Every directory in the tree at the time the watcher starts gets
+    /// watched, so zones in directories created later are only checked
+    /// when the cache is refreshed by hand.
+    #[derive(Debug)]
+    pub struct Watcher {
+        wake: UnixStream,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl ZoneCache {
+
+        /// Starts watching the cache’s zoneinfo tree for changes, refreshing
+        /// the cache whenever a file in it gets written, moved, or removed.
+        pub fn watch(cache: &Arc<ZoneCache>) -> Result<Watcher> {
+            let inotify = Inotify::init()?;
+            let mut watches = inotify.watches();
+
+            let mut directories = Vec::new();
+            find_directories(cache.root(), &mut directories)?;
+
+            let mask = WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM
+                     | WatchMask::CREATE | WatchMask::DELETE | WatchMask::ATTRIB;
+
+            for directory in directories {
+                watches.add(directory, mask)?;
+            }
+
+            let (wake, woken) = UnixStream::pair()?;
+            let cache = Arc::downgrade(cache);
+            let thread = thread::spawn(move || run(inotify, &woken, &cache));
+
+            Ok(Watcher { wake, thread: Some(thread) })
+        }
+    }
+
+    /// Refreshes the cache after each batch of events, until the other end
+    /// of the given socket gets written to or closed.
+    fn run(mut inotify: Inotify, woken: &UnixStream, cache: &Weak<ZoneCache>) {
+        let mut buffer = [0; 4096];
+
+        loop {
+            let mut fds = [
+                libc::pollfd { fd: inotify.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+                libc::pollfd { fd: woken.as_raw_fd(),   events: libc::POLLIN, revents: 0 },
+            ];
+
+            // The array outlives the call, and its length is the one given.
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if ready < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return;
+            }
+
+            if fds[1].revents != 0 {
+                return;
+            }
+
+            if fds[0].revents == 0 {
+                continue;
+            }
+
+            // The descriptor doesn’t block, so this reads whatever events
+            // have built up, and then stops.
+            loop {
+                match inotify.read_events(&mut buffer) {
+                    Ok(_) => {},
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(_) => return,
+                }
+            }
+
+            match cache.upgrade() {
+                Some(cache) => { let _ = cache.refresh(); },
+                None        => return,
+            }
+        }
+    }
+
+    /// Collects the given directory and every directory under it.
+    fn find_directories(directory: &Path, directories: &mut Vec<PathBuf>) -> io::Result<()> {
+        directories.push(directory.to_path_buf());
+
+        for entry in directory.read_dir()? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                find_directories(&entry.path(), directories)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writing to the socket wakes the thread up, whatever state the
+    /// watches are in, and shutting it down makes sure it stays woken even
+    /// if the write fails.
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            let _ = self.wake.write_all(&[0]);
+            let _ = self.wake.shutdown(::std::net::Shutdown::Both);
+
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::empty_tree;
+
+    use TZDataBuilder;
+
+    fn write_zone(root: &Path, offset: i64) {
+        let mut builder = TZDataBuilder::new();
+        builder.add_type("TST", offset, false);
+        let bytes = ::writer::write(&builder.build_raw().unwrap());
+
+        // Write a new file and move it into place, as package managers do.
+        fs::create_dir_all(root.join("Test")).unwrap();
+        let temporary = root.join("Test/Zone.new");
+        fs::write(&temporary, bytes).unwrap();
+        fs::rename(&temporary, root.join("Test/Zone")).unwrap();
+    }
+
+    #[test]
+    fn caching() {
+        let root = empty_tree("cache", "caching");
+        fs::write(root.join("+VERSION"), "2025b\n").unwrap();
+        write_zone(&root, 3600);
+
+        let cache = ZoneCache::new(&root);
+        assert_eq!(cache.version(), Some(version::parse("2025b").unwrap()));
+
+        let first = cache.get("Test/Zone").unwrap();
+        assert_eq!(first.name, Some("Test/Zone".to_owned()));
+        assert!(Arc::ptr_eq(&first, &cache.get("Test/Zone").unwrap()));
+        assert!(cache.get("Test/Missing").is_err());
+        assert!(cache.get("../Zone").is_err());
+        assert_eq!(cache.names(), vec![ "Test/Zone".to_owned() ]);
+
+        // The same contents in a new file don’t count as a change.
+        write_zone(&root, 3600);
+        assert!(cache.refresh().unwrap().is_empty());
+        assert!(Arc::ptr_eq(&first, &cache.get("Test/Zone").unwrap()));
+
+        write_zone(&root, 7200);
+        fs::write(root.join("+VERSION"), "2025c\n").unwrap();
+        assert_eq!(cache.refresh().unwrap(), vec![ "Test/Zone".to_owned() ]);
+        assert_eq!(cache.get("Test/Zone").unwrap().local_time_types[0].offset, 7200);
+        assert_eq!(first.local_time_types[0].offset, 3600);
+        assert_eq!(cache.version(), Some(version::parse("2025c").unwrap()));
+
+        // A file that can’t be parsed any more keeps its old data, without
+        // stopping the other zones from being refreshed, and gets tried
+        // again next time.
+        fs::copy(root.join("Test/Zone"), root.join("Test/Other")).unwrap();
+        cache.get("Test/Other").unwrap();
+        fs::write(root.join("Test/Other"), b"TZif").unwrap();
+        write_zone(&root, 3600);
+        assert_eq!(cache.refresh().unwrap(), vec![ "Test/Zone".to_owned() ]);
+        assert_eq!(cache.get("Test/Other").unwrap().local_time_types[0].offset, 7200);
+
+        fs::copy(root.join("Test/Zone"), root.join("Test/Other")).unwrap();
+        assert_eq!(cache.refresh().unwrap(), vec![ "Test/Other".to_owned() ]);
+        assert_eq!(cache.get("Test/Other").unwrap().local_time_types[0].offset, 3600);
+
+        fs::remove_file(root.join("Test/Zone")).unwrap();
+        fs::remove_file(root.join("Test/Other")).unwrap();
+        assert_eq!(cache.refresh().unwrap(), vec![ "Test/Other".to_owned(), "Test/Zone".to_owned() ]);
+        assert!(cache.names().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn inaccessible() {
+        let root = empty_tree("cache", "inaccessible");
+        write_zone(&root, 3600);
+
+        let cache = ZoneCache::new(&root);
+        cache.get("Test/Zone").unwrap();
+
+        // With a file in place of its directory, the zone’s file can’t be
+        // looked at, but that doesn’t mean it’s gone.
+        fs::rename(root.join("Test"), root.join("Test.old")).unwrap();
+        fs::write(root.join("Test"), b"").unwrap();
+        assert!(cache.refresh().unwrap().is_empty());
+        assert_eq!(cache.names(), vec![ "Test/Zone".to_owned() ]);
+
+        fs::remove_file(root.join("Test")).unwrap();
+        assert_eq!(cache.refresh().unwrap(), vec![ "Test/Zone".to_owned() ]);
+        assert!(cache.names().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    #[test]
+    fn watching() {
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        let root = empty_tree("cache", "watching");
+        write_zone(&root, 3600);
+
+        let cache = Arc::new(ZoneCache::new(&root));
+        assert_eq!(cache.get("Test/Zone").unwrap().local_time_types[0].offset, 3600);
+
+        let watcher = ZoneCache::watch(&cache).unwrap();
+        write_zone(&root, 7200);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while cache.get("Test/Zone").unwrap().local_time_types[0].offset != 7200 {
+            assert!(Instant::now() < deadline, "cache was not refreshed");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        drop(watcher);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(all(feature = "watch", target_os = "linux"))]
+    #[test]
+    fn dropping_after_removal() {
+        let root = empty_tree("cache", "dropping-after-removal");
+        write_zone(&root, 3600);
+
+        // Removing the tree takes the watches with it, so there’s nothing
+        // left for dropping the watcher to remove.
+        let cache = Arc::new(ZoneCache::new(&root));
+        let watcher = ZoneCache::watch(&cache).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        drop(watcher);
+    }
+}
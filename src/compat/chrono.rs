@@ -0,0 +1,257 @@
+//! Using parsed zones with `chrono`
+//!
+//! The `Tz` type wraps a zone’s data so it can be used as a
+//! `chrono::TimeZone`, giving `DateTime<Tz>` values whose offsets come from
+//! the zone’s transitions, and from its footer after the last one. It’s
+//! cheap to clone, as the data is shared.
+
+use std::convert::TryFrom;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::result;
+use std::sync::Arc;
+
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone};
+use chrono::LocalResult as ChronoLocalResult;
+
+use posix::PosixTz;
+use {LocalResult, LocalTimeType, Result, TZData, TypeSource};
+
+
+/// A zone that can be used as a `chrono` time zone.
+#[derive(Debug, Clone)]
+pub struct Tz {
+    data: Arc<TZData>,
+    footer: Option<Arc<PosixTz>>,
+}
+
+impl Tz {
+
+    /// Wraps a zone’s data, returning an error if any of its offsets are
+    /// too large for `chrono` to represent (a day or more from UTC).
+    pub fn new(data: TZData) -> Result<Tz> {
+        let footer = data.footer_rule();
+
+        let mut offsets: Vec<i64> = data.local_time_types.iter().map(|ltt| ltt.offset).collect();
+        if let Some(ref footer) = footer {
+            offsets.push(footer.std.offset);
+            offsets.extend(footer.dst.as_ref().map(|dst| dst.local_time_type.offset));
+        }
+
+        for offset in offsets {
+            if fixed_offset(offset).is_none() {
+                return Err(Box::new(Error::OffsetOutOfRange { offset }));
+            }
+        }
+
+        Ok(Tz { data: Arc::new(data), footer })
+    }
+
+    /// Returns the zone’s data.
+    pub fn data(&self) -> &TZData {
+        &self.data
+    }
+
+    /// Returns the name of the zone, such as "Europe/Paris", if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.data.name.as_deref()
+    }
+
+    fn offset(&self, source: TypeSource) -> TzOffset {
+        TzOffset { tz: self.clone(), source }
+    }
+}
+
+impl TimeZone for Tz {
+    type Offset = TzOffset;
+
+    fn from_offset(offset: &TzOffset) -> Tz {
+        offset.tz.clone()
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> ChronoLocalResult<TzOffset> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> ChronoLocalResult<TzOffset> {
+        match self.data.resolve_local_sources(self.footer.as_deref(), local.and_utc().timestamp()) {
+            LocalResult::None             => ChronoLocalResult::None,
+            LocalResult::Single(i)        => ChronoLocalResult::Single(self.offset(i)),
+            LocalResult::Ambiguous(i, j)  => ChronoLocalResult::Ambiguous(self.offset(i), self.offset(j)),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> TzOffset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> TzOffset {
+        self.offset(self.data.source_at(self.footer.as_deref(), utc.and_utc().timestamp()))
+    }
+}
+
+
+/// The offset in effect in a `Tz` at a particular time.
+#[derive(Debug, Clone)]
+pub struct TzOffset {
+
+    /// The zone this offset is from.
+    tz: Tz,
+
+    /// Where the local time type that this offset is from comes from.
+    source: TypeSource,
+}
+
+impl TzOffset {
+
+    /// Returns the local time type in effect.
+    pub fn local_time_type(&self) -> &LocalTimeType {
+        self.tz.data.source_type(self.tz.footer.as_deref(), self.source)
+    }
+
+    /// Returns the abbreviation in use, such as “GMT” or “PDT”.
+    pub fn abbreviation(&self) -> &str {
+        &self.local_time_type().name
+    }
+
+    /// Returns whether daylight-saving time is in effect.
+    pub fn is_dst(&self) -> bool {
+        self.local_time_type().is_dst
+    }
+}
+
+impl Offset for TzOffset {
+    fn fix(&self) -> FixedOffset {
+        let offset = self.local_time_type().offset;
+        fixed_offset(offset).expect("offsets are checked when the zone is created")
+    }
+}
+
+impl fmt::Display for TzOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        f.write_str(self.abbreviation())
+    }
+}
+
+
+/// Converts an offset in seconds to `chrono`’s representation, if it’s in
+/// range.
+fn fixed_offset(offset: i64) -> Option<FixedOffset> {
+    i32::try_from(offset).ok().and_then(FixedOffset::east_opt)
+}
+
+
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when one of a zone’s offsets is too large for `chrono`.
+    OffsetOutOfRange {
+
+        /// The offset that was out of range, in seconds.
+        offset: i64,
+    },
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::OffsetOutOfRange { .. } => "offset out of range",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::OffsetOutOfRange { offset } => write!(f, "offset of {} seconds is out of range", offset),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::ltt;
+
+    /// London in 2021: BST from 01:00 UTC on 28 March to 01:00 UTC on
+    /// 31 October.
+    fn london_data() -> TZData {
+        let mut data = TZData::from_timespans(ltt(0, false, "GMT"), vec![
+            (1_616_893_200, ltt(3600, true, "BST")),
+            (1_635_642_000, ltt(0, false, "GMT")),
+        ]);
+
+        data.set_name("Europe/London");
+        data
+    }
+
+    fn london() -> Tz {
+        Tz::new(london_data()).unwrap()
+    }
+
+    fn local(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2021, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn utc() {
+        let tz = london();
+        let offset = tz.offset_from_utc_datetime(&local(7, 1, 12, 0));
+        assert_eq!(offset.fix(), FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(offset.to_string(), "BST");
+        assert!(offset.is_dst());
+
+        let offset = tz.offset_from_utc_datetime(&local(3, 28, 0, 59));
+        assert_eq!(offset.abbreviation(), "GMT");
+    }
+
+    #[test]
+    fn gap() {
+        let tz = london();
+        assert!(matches!(tz.offset_from_local_datetime(&local(3, 28, 1, 30)), ChronoLocalResult::None));
+        assert!(matches!(tz.offset_from_local_datetime(&local(3, 28, 2, 0)), ChronoLocalResult::Single(ref o) if o.abbreviation() == "BST"));
+    }
+
+    #[test]
+    fn fold() {
+        let tz = london();
+        match tz.offset_from_local_datetime(&local(10, 31, 1, 30)) {
+            ChronoLocalResult::Ambiguous(earlier, later) => {
+                assert_eq!(earlier.abbreviation(), "BST");
+                assert_eq!(later.abbreviation(), "GMT");
+            },
+            _ => panic!("expected an ambiguous result"),
+        }
+
+        let time = tz.from_local_datetime(&local(10, 31, 2, 0)).unwrap();
+        assert_eq!(time.timestamp(), 1_635_645_600);
+    }
+
+    #[test]
+    fn footer() {
+        let data = ::parse(include_bytes!("../../tests/fixtures/london-slim.tzif").to_vec()).unwrap();
+        assert!(data.transitions.last().unwrap().timestamp < 946_684_800);
+        let tz = Tz::new(data).unwrap();
+
+        let summer = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let offset = tz.offset_from_utc_datetime(&summer);
+        assert_eq!((offset.abbreviation(), offset.fix()), ("BST", FixedOffset::east_opt(3600).unwrap()));
+
+        let winter = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        assert_eq!(tz.offset_from_utc_datetime(&winter).abbreviation(), "GMT");
+
+        let gap = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(1, 30, 0).unwrap();
+        assert!(matches!(tz.offset_from_local_datetime(&gap), ChronoLocalResult::None));
+
+        let fold = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap().and_hms_opt(1, 30, 0).unwrap();
+        assert!(matches!(tz.offset_from_local_datetime(&fold), ChronoLocalResult::Ambiguous(ref a, ref b) if (a.abbreviation(), b.abbreviation()) == ("BST", "GMT")));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut data = london_data();
+        data.local_time_types[0].offset = 86_400;
+        assert!(Tz::new(data).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Using parsed zones with `datetime`
+//!
+//! This is where the `CompiledData` trait lives, which reads zones straight
+//! into `datetime`’s `TimeZone` type, along with the conversions it uses to
+//! get there.
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+
+use datetime::zone::{TimeZone, TimeZoneSource, FixedTimespan};
+use datetime::zone::TimeType as DatetimeTimeType;
+use datetime::zone::runtime::{OwnedTimeZone, OwnedFixedTimespanSet};
+
+use {LocalTimeType, Result, TimeType, TZData};
+use {read_file, zone_name, zoneinfo_path, DEFAULT_POSIX_YEARS};
+use zi::days_from_civil;
+
+
+pub trait CompiledData {
+    fn parse(input: Vec<u8>) -> Result<TimeZone>;
+
+    /// Parses a series of bytes into a time zone with the given name, such
+    /// as "Europe/Paris".
+    fn parse_named(input: Vec<u8>, name: &str) -> Result<TimeZone>;
+
+    fn from_file<P: AsRef<Path>>(path: P) -> Result<TimeZone> {
+        let contents = read_file(path)?;
+        let tz = Self::parse(contents)?;
+        Ok(tz)
+    }
+
+    /// Reads a file from inside the zoneinfo tree under the given root,
+    /// naming the time zone after its path relative to the root.
+    ///
+    /// For example, reading `/usr/share/zoneinfo/Europe/Paris` with a root of
+    /// `/usr/share/zoneinfo` produces a time zone named "Europe/Paris".
+    /// Symbolic links, such as `/etc/localtime`, get followed into the tree
+    /// if the path itself isn’t inside it.
+    fn from_file_named<R: AsRef<Path>, P: AsRef<Path>>(root: R, path: P) -> Result<TimeZone> {
+        let name = zone_name(root, &path)?;
+        let contents = read_file(path)?;
+        let tz = Self::parse_named(contents, &name)?;
+        Ok(tz)
+    }
+
+    /// Reads the zone with the given name, such as "Europe/Paris", from the
+    /// zoneinfo tree under the given root.
+    fn from_zoneinfo<R: AsRef<Path>>(root: R, name: &str) -> Result<TimeZone> {
+        let contents = read_file(zoneinfo_path(root, name)?)?;
+        let tz = Self::parse_named(contents, name)?;
+        Ok(tz)
+    }
+
+    /// Builds a time zone out of a POSIX TZ string, such as the value of
+    /// the `TZ` environment variable, with the transitions its rule
+    /// produces from 1970 to 2037.
+    fn from_posix_tz(tz: &str) -> Result<TimeZone> {
+        let data = TZData::from_posix_tz(tz)?;
+        Ok(runtime_time_zone(&data))
+    }
+}
+
+impl CompiledData for TimeZone {
+    fn parse(input: Vec<u8>) -> Result<TimeZone> {
+        let data = ::parse(input)?;
+        Ok(runtime_time_zone(&data))
+    }
+
+    fn parse_named(input: Vec<u8>, name: &str) -> Result<TimeZone> {
+        let data = ::parse_named(input, name)?;
+        Ok(runtime_time_zone(&data))
+    }
+}
+
+/// Wraps a set of zone data up as a `datetime` time zone.
+pub(crate) fn runtime_time_zone(tz: &TZData) -> TimeZone {
+    TimeZone(TimeZoneSource::Runtime(Arc::new(to_time_zone(tz))))
+}
+
+
+/// Converts a set of zone data into `datetime`’s representation, which
+/// has the zone’s name and a timespan for each transition.
+///
+/// As `datetime` has no way to follow a rule, the footer’s transitions
+/// get listed up to the end of 2037, as `zic -b fat` does, starting after
+/// the last transition or at the start of 1970, whichever is later.
+pub fn to_time_zone(tz: &TZData) -> OwnedTimeZone {
+    let mut rest: Vec<_> = tz.transitions.iter().map(|t| {
+        (t.timestamp, to_fixed_timespan(&tz.local_time_types[t.local_time_type]))
+    }).collect();
+
+    if let Some(footer) = tz.footer_rule() {
+        let after_last = tz.transitions.last().map_or(i64::MIN, |t| t.timestamp.saturating_add(1));
+        let start = after_last.max(days_from_civil(*DEFAULT_POSIX_YEARS.start(), 1, 1) * 86400);
+        let end = days_from_civil(DEFAULT_POSIX_YEARS.end() + 1, 1, 1) * 86400;
+
+        if start < end {
+            let current = &tz.local_time_types[tz.transitions.last().map_or(0, |t| t.local_time_type)];
+            let at_start = footer.local_time_type_at(start);
+            if (&at_start.name, at_start.offset, at_start.is_dst) != (&current.name, current.offset, current.is_dst) {
+                rest.push((start, to_fixed_timespan(at_start)));
+            }
+
+            rest.extend(footer.transitions(start + 1 .. end).into_iter().map(|(t, ltt)| (t, to_fixed_timespan(ltt))));
+        }
+    }
+
+    OwnedTimeZone {
+        name: tz.name.clone(),
+        fixed_timespans: OwnedFixedTimespanSet {
+            first: to_fixed_timespan(&tz.local_time_types[0]),
+            rest,
+        },
+    }
+}
+
+/// Convert a local time type into datetime’s `FixedTimespan`
+/// representation.
+///
+/// It doesn’t actually contain any `'static` data, but if the lifetime is
+/// not specified, Rust ties its lifetime to the type, when they’re actually
+/// completely unrelated.
+pub fn to_fixed_timespan(ltt: &LocalTimeType) -> FixedTimespan<'static> {
+    FixedTimespan {
+        offset: ltt.offset,
+        is_dst: ltt.is_dst,
+        name: Cow::Owned(ltt.name.clone()),
+    }
+}
+
+
+impl From<TimeType> for DatetimeTimeType {
+    fn from(time_type: TimeType) -> DatetimeTimeType {
+        match time_type {
+            TimeType::Wall      => DatetimeTimeType::Wall,
+            TimeType::Standard  => DatetimeTimeType::Standard,
+            TimeType::UTC       => DatetimeTimeType::UTC,
+        }
+    }
+}
+
+impl From<DatetimeTimeType> for TimeType {
+    fn from(time_type: DatetimeTimeType) -> TimeType {
+        match time_type {
+            DatetimeTimeType::Wall      => TimeType::Wall,
+            DatetimeTimeType::Standard  => TimeType::Standard,
+            DatetimeTimeType::UTC       => TimeType::UTC,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::ltt;
+
+    #[test]
+    fn time_zone() {
+        let mut data = TZData::from_timespans(ltt(32400, false, "JST"), vec![
+            (-683_794_800, ltt(36000, true, "JDT")),
+            (-672_393_600, ltt(32400, false, "JST")),
+        ]);
+        data.set_name("Asia/Tokyo");
+
+        let tz = to_time_zone(&data);
+        assert_eq!(tz.name, Some("Asia/Tokyo".to_owned()));
+        assert_eq!(tz.fixed_timespans.first.name, "JST");
+        assert_eq!(tz.fixed_timespans.rest[0], (-683_794_800, FixedTimespan { offset: 36000, is_dst: true, name: Cow::Borrowed("JDT") }));
+        assert_eq!(tz.fixed_timespans.rest[1].1, tz.fixed_timespans.first);
+    }
+
+    #[test]
+    fn footer() {
+        let data = ::parse(include_bytes!("../../tests/fixtures/london-slim.tzif").to_vec()).unwrap();
+        let last = data.transitions.last().unwrap().timestamp;
+        let tz = to_time_zone(&data);
+
+        // The file’s last transition is in March 1996, so the footer’s
+        // transitions start in October, with two a year after that until
+        // 2037, the same as in the fat file.
+        let extrapolated: Vec<_> = tz.fixed_timespans.rest.iter().filter(|t| t.0 > last).collect();
+        assert_eq!(extrapolated.len(), 83);
+        assert_eq!(extrapolated[0], &(846_378_000, FixedTimespan { offset: 0, is_dst: false, name: Cow::Borrowed("GMT") }));
+        assert_eq!(extrapolated[82], &(2_140_045_200, FixedTimespan { offset: 0, is_dst: false, name: Cow::Borrowed("GMT") }));
+
+        let fat = to_time_zone(&::parse(include_bytes!("../../tests/fixtures/london-fat.tzif").to_vec()).unwrap());
+        assert_eq!(tz.fixed_timespans.rest.len(), fat.fixed_timespans.rest.len());
+    }
+
+    #[test]
+    fn rule_only() {
+        let tz = to_time_zone(&TZData::from_posix_tz_rule("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap());
+        assert_eq!(tz.fixed_timespans.first.name, "AEST");
+        assert_eq!(tz.fixed_timespans.rest[0], (0, FixedTimespan { offset: 39600, is_dst: true, name: Cow::Borrowed("AEDT") }));
+        assert_eq!(tz.fixed_timespans.rest.len(), 1 + 2 * 68);
+    }
+
+    #[test]
+    fn time_types() {
+        for &time_type in &[ TimeType::Wall, TimeType::Standard, TimeType::UTC ] {
+            assert_eq!(TimeType::from(DatetimeTimeType::from(time_type)), time_type);
+        }
+    }
+}
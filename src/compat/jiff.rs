@@ -0,0 +1,278 @@
+//! Using parsed zones with `jiff`
+//!
+//! `jiff` can only build a time zone with transitions from the bytes of a
+//! zoneinfo file, so converting a zone to `jiff` writes it out again with
+//! the `writer` module, footer included. Converting the other way walks the
+//! `jiff` zone’s transitions, and works out its footer from them.
+
+use jiff::Timestamp;
+use jiff::fmt::temporal::DateTimePrinter;
+use jiff::tz::{Dst, Offset, TimeZone};
+
+use {Indicators, LocalTimeType, Result, TZData, DEFAULT_POSIX_YEARS};
+use parser;
+use posix;
+use writer;
+use zi::days_from_civil;
+
+
+/// The number of years that a footer worked out from a `jiff` zone gets
+/// checked over: a whole cycle of the Gregorian calendar.
+const CHECKED_YEARS: i64 = 400;
+
+
+/// Converts a zone into a `jiff` time zone, with the zone’s name if it has
+/// one. Times after the last transition are handled by the footer.
+pub fn to_jiff(tz: &TZData) -> Result<TimeZone> {
+    let bytes = writer::to_bytes(tz)?;
+    let name = tz.name.as_deref().unwrap_or("");
+    Ok(TimeZone::tzif(name, &bytes)?)
+}
+
+/// Converts the internal structure of a zoneinfo file, such as one read by
+/// `parser::parse` with custom limits, into a `jiff` time zone with the
+/// given name, without interpreting it first.
+pub fn parsed_to_jiff(name: &str, tz: &parser::TZData) -> Result<TimeZone> {
+    Ok(TimeZone::tzif(name, &writer::write(tz))?)
+}
+
+/// Converts a `jiff` time zone into a zone, with the time zone’s name if it
+/// has one.
+///
+/// A time zone made from a POSIX TZ string becomes the same zone as
+/// `TZData::from_posix_tz` makes from it. Otherwise, as `jiff` doesn’t make
+/// a zone’s footer available, the zone’s transitions are listed up until
+/// the end of 2037, and the footer gets worked out from the transitions
+/// after that. If no TZ string gives the same transitions, the zone has no
+/// footer.
+pub fn from_jiff(tz: &TimeZone) -> Result<TZData> {
+    if let Some(string) = posix_string(tz) {
+        if let Ok(data) = TZData::from_posix_tz(&string) {
+            return Ok(data);
+        }
+    }
+
+    let info = tz.to_offset_info(Timestamp::MIN);
+    let first = local_time_type(info.offset(), info.dst(), info.abbreviation());
+
+    let end = days_from_civil(DEFAULT_POSIX_YEARS.end() + 1, 1, 1) * 86400;
+    let until = Timestamp::from_second(end)?;
+    let rest = tz.following(Timestamp::MIN)
+                 .take_while(|t| t.timestamp() < until)
+                 .map(|t| (t.timestamp().as_second(), local_time_type(t.offset(), t.dst(), t.abbreviation())))
+                 .collect();
+
+    let mut data = TZData::from_timespans(first, rest);
+    data.name = tz.iana_name().map(str::to_owned);
+    data.footer = footer(tz, end);
+    Ok(data)
+}
+
+/// Returns the TZ string that a `jiff` time zone was made from, if it was
+/// made from one.
+fn posix_string(tz: &TimeZone) -> Option<String> {
+    if tz.iana_name().is_some() || tz.to_fixed_offset().is_ok() {
+        return None;
+    }
+
+    let mut string = String::new();
+    DateTimePrinter::new().print_time_zone(tz, &mut string).ok()?;
+    Some(string)
+}
+
+/// Works out the footer of a `jiff` time zone from its transitions in the
+/// year starting at the given timestamp, then checks that the footer gives
+/// the same transitions as the zone for `CHECKED_YEARS` years.
+fn footer(tz: &TimeZone, start: i64) -> Option<String> {
+    let info = tz.to_offset_info(Timestamp::from_second(start).ok()?);
+    let at_start = local_time_type(info.offset(), info.dst(), info.abbreviation());
+
+    let mut candidates = Vec::new();
+    match changes(tz, &at_start, start, start + 366 * 86400)?[..] {
+        [] if !at_start.is_dst => candidates.push(posix::format_fixed(&at_start)),
+
+        [ref a, ref b] if a.1.is_dst != b.1.is_dst => {
+            let (dst, std) = if a.1.is_dst { (a, b) } else { (b, a) };
+
+            let mut names = posix::format_abbreviation(&std.1.name) + &posix::format_time(-std.1.offset);
+            names += &posix::format_abbreviation(&dst.1.name);
+            if dst.1.offset - std.1.offset != 60 * 60 {
+                names += &posix::format_time(-dst.1.offset);
+            }
+
+            // Each change is in the local time that was in effect before it.
+            for start_date in rule_dates(dst.0 + std.1.offset) {
+                for end_date in rule_dates(std.0 + dst.1.offset) {
+                    candidates.push(format!("{},{},{}", names, start_date, end_date));
+                }
+            }
+        },
+
+        _ => return None,
+    }
+
+    let end = days_from_civil(DEFAULT_POSIX_YEARS.end() + 1 + CHECKED_YEARS, 1, 1) * 86400;
+    let transitions = changes(tz, &at_start, start, end)?;
+
+    candidates.into_iter().find(|candidate| {
+        let rule = match posix::parse(candidate) {
+            Ok(rule)  => rule,
+            Err(_)    => return false,
+        };
+
+        *rule.local_time_type_at(start) == at_start
+            && rule.transitions(start + 1 .. end).into_iter().map(|(t, ltt)| (t, ltt.clone())).eq(transitions.iter().cloned())
+    })
+}
+
+/// Returns the transitions of a `jiff` time zone after `start` and before
+/// `end`, leaving out any that don’t change the local time type, such as
+/// the one that zic adds at the end of 32-bit time.
+fn changes(tz: &TimeZone, at_start: &LocalTimeType, start: i64, end: i64) -> Option<Vec<(i64, LocalTimeType)>> {
+    let until = Timestamp::from_second(end).ok()?;
+    let mut changes: Vec<(i64, LocalTimeType)> = Vec::new();
+
+    for t in tz.following(Timestamp::from_second(start).ok()?).take_while(|t| t.timestamp() < until) {
+        let ltt = local_time_type(t.offset(), t.dst(), t.abbreviation());
+        if ltt != *changes.last().map_or(at_start, |c| &c.1) {
+            changes.push((t.timestamp().as_second(), ltt));
+        }
+    }
+
+    Some(changes)
+}
+
+/// Returns the ways that a TZ string’s rule can give the day and time of a
+/// change at the given local time, in seconds since the Unix epoch.
+///
+/// A day late in the month can be either the fourth of its weekday in the
+/// month or the last one. As zic gives some rules as a time on a nearby
+/// day instead, such as `M3.4.4/26` for the Friday before the last Sunday,
+/// the days up to two either side get tried too, after the day itself.
+fn rule_dates(local: i64) -> Vec<String> {
+    let mut dates = Vec::new();
+
+    for &days in &[ 0, 1, -1, 2, -2 ] {
+        let shifted = match Timestamp::from_second(local - days * 86400) {
+            Ok(timestamp)  => Offset::UTC.to_datetime(timestamp),
+            Err(_)         => continue,
+        };
+
+        let day = shifted.day();
+        let mut weeks = vec![ (day - 1) / 7 + 1 ];
+        if day + 7 > shifted.days_in_month() && weeks[0] != 5 {
+            weeks.push(5);
+        }
+
+        let seconds = days * 86400 + i64::from(shifted.hour()) * 3600 + i64::from(shifted.minute()) * 60 + i64::from(shifted.second());
+        let time = if seconds == 2 * 60 * 60 { String::new() } else { format!("/{}", posix::format_time(seconds)) };
+        let weekday = shifted.weekday().to_sunday_zero_offset();
+
+        dates.extend(weeks.into_iter().map(|week| format!("M{}.{}.{}{}", shifted.month(), week, weekday, time)));
+    }
+
+    dates
+}
+
+fn local_time_type(offset: Offset, dst: Dst, abbreviation: &str) -> LocalTimeType {
+    LocalTimeType {
+        name:             abbreviation.to_owned(),
+        offset:           i64::from(offset.seconds()),
+        is_dst:           dst.is_dst(),
+        indicators:       Indicators::UNSPECIFIED,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::ltt;
+
+    fn london() -> TZData {
+        let mut data = TZData::from_timespans(ltt(-75, false, "LMT"), vec![
+            (-3_852_662_325, ltt(0, false, "GMT")),
+        ]);
+
+        data.set_name("Europe/London");
+        data.footer = Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned());
+        data
+    }
+
+    #[test]
+    fn footer() {
+        let tz = to_jiff(&london()).unwrap();
+        assert_eq!(tz.iana_name(), Some("Europe/London"));
+
+        // 2030-07-01, long after the last transition
+        let summer = Timestamp::from_second(1_909_180_800).unwrap();
+        assert_eq!(tz.to_offset(summer).seconds(), 3600);
+
+        let before = Timestamp::from_second(-3_852_662_326).unwrap();
+        assert_eq!(tz.to_offset(before).seconds(), -75);
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = from_jiff(&to_jiff(&london()).unwrap()).unwrap();
+        assert_eq!(data.name, Some("Europe/London".to_owned()));
+        assert_eq!(data.local_time_types[0].name, "LMT");
+        assert_eq!(data.transitions[0], london().transitions[0]);
+
+        assert_eq!(data.footer, london().footer);
+
+        // The first transition, then two a year from the footer, from 1848
+        // to 2037
+        assert_eq!(data.transitions.len(), 1 + 2 * 190);
+        let last = data.transitions.last().unwrap();
+        assert_eq!(last.timestamp, 2_140_045_200);
+        assert_eq!(data.local_time_types[last.local_time_type].name, "GMT");
+    }
+
+    #[test]
+    fn fixed() {
+        let data = from_jiff(&TimeZone::fixed(jiff::tz::offset(-5))).unwrap();
+        assert_eq!(data.local_time_types[0].offset, -18000);
+        assert!(data.transitions.is_empty());
+        assert_eq!(data.footer, Some("<-05>5".to_owned()));
+    }
+
+    #[test]
+    fn posix() {
+        let tz = TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(from_jiff(&tz).unwrap(), TZData::from_posix_tz("EST5EDT,M3.2.0,M11.1.0").unwrap());
+    }
+
+    #[test]
+    fn inferred_footers() {
+        // Rules with their changes on another day from the one they name
+        // can come back as another rule that gives the same transitions.
+        for &(footer, expected) in &[
+            ("AEST-10AEDT,M10.1.0,M4.1.0/3",          "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+            ("<+1030>-10:30<+11>-11,M10.1.0,M4.1.0",  "<+1030>-10:30<+11>-11,M10.1.0,M4.1.0"),
+            ("EST5EDT,M3.4.0,M11.1.0",                "EST5EDT,M3.4.0,M11.1.0"),
+            ("IST-2IDT,M3.4.4/26,M10.5.0",            "IST-2IDT,M3.4.4/26,M10.5.0"),
+            ("<-02>2<-01>,M3.5.0/-1,M10.5.0/0",       "<-02>2<-01>,M3.5.0/-1,M10.5.0/0"),
+            ("EET-2EEST,M3.4.4/50,M10.4.4/50",        "EET-2EEST,M3.5.0/-22,M10.5.0/-22"),
+        ] {
+            let mut data = TZData::from_timespans(ltt(0, false, "LMT"), vec![ (0, ltt(3600, false, "X")) ]);
+            data.footer = Some(footer.to_owned());
+
+            let round_trip = from_jiff(&to_jiff(&data).unwrap()).unwrap();
+            assert_eq!(round_trip.footer.as_deref(), Some(expected));
+        }
+
+        // A rule whose time moves the change more than two days from the
+        // one it names can’t be worked out, so there’s no footer.
+        let mut data = TZData::from_timespans(ltt(0, false, "LMT"), vec![ (0, ltt(-18000, false, "EST")) ]);
+        data.footer = Some("EST5EDT,M3.2.0,M11.1.0/99".to_owned());
+        assert_eq!(from_jiff(&to_jiff(&data).unwrap()).unwrap().footer, None);
+    }
+
+    #[test]
+    fn parsed() {
+        let raw = writer::uncook(&london()).unwrap();
+        let tz = parsed_to_jiff("Europe/London", &raw).unwrap();
+        assert_eq!(tz.to_offset(Timestamp::from_second(0).unwrap()).seconds(), 0);
+    }
+}
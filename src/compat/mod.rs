@@ -0,0 +1,16 @@
+//! Using parsed zones with other date and time libraries
+//!
+//! Each of these modules is only available when the Cargo feature with the
+//! same name is enabled.
+
+#[cfg(feature = "chrono")]
+pub mod chrono;
+
+#[cfg(feature = "datetime")]
+pub mod datetime;
+
+#[cfg(feature = "jiff")]
+pub mod jiff;
+
+#[cfg(feature = "time")]
+pub mod time;
@@ -0,0 +1,155 @@
+//! Using parsed zones with `time`
+//!
+//! The `time` crate has no time zone type of its own, only fixed offsets,
+//! so these functions look up the offset a zone has at a particular time
+//! and attach it to the `time` types.
+
+use std::convert::TryFrom;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::result;
+
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use {LocalResult, Result, TZData};
+
+
+/// Returns the offset from UTC that the zone has at the given instant.
+pub fn utc_offset_at(tz: &TZData, instant: OffsetDateTime) -> Result<UtcOffset> {
+    utc_offset(tz.local_time_type_at(instant.unix_timestamp()).offset)
+}
+
+/// Converts an instant to the local date and time in the zone.
+pub fn to_offset_datetime(tz: &TZData, utc: OffsetDateTime) -> Result<OffsetDateTime> {
+    let offset = utc_offset_at(tz, utc)?;
+    utc.checked_to_offset(offset)
+       .ok_or_else(|| Error::DateOutOfRange.into())
+}
+
+/// Returns the instants that a local date and time in the zone could refer
+/// to: none if it was skipped over when the clocks went forward, or two if
+/// it happened twice when they went back.
+pub fn from_local(tz: &TZData, local: PrimitiveDateTime) -> Result<LocalResult<OffsetDateTime>> {
+    let local_time_types = tz.resolve_local(local.assume_utc().unix_timestamp());
+
+    Ok(match local_time_types {
+        LocalResult::None             => LocalResult::None,
+        LocalResult::Single(a)        => LocalResult::Single(local.assume_offset(utc_offset(a.offset)?)),
+        LocalResult::Ambiguous(a, b)  => LocalResult::Ambiguous(local.assume_offset(utc_offset(a.offset)?),
+                                                                local.assume_offset(utc_offset(b.offset)?)),
+    })
+}
+
+
+/// Converts an offset in seconds to `time`’s representation, if it’s in
+/// range.
+fn utc_offset(offset: i64) -> Result<UtcOffset> {
+    i32::try_from(offset).ok()
+                         .and_then(|o| UtcOffset::from_whole_seconds(o).ok())
+                         .ok_or_else(|| Error::OffsetOutOfRange { offset }.into())
+}
+
+
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when one of a zone’s offsets is too large for `time`.
+    OffsetOutOfRange {
+
+        /// The offset that was out of range, in seconds.
+        offset: i64,
+    },
+
+    /// The error when converting a date to local time takes it outside of
+    /// the range `time` can represent.
+    DateOutOfRange,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::OffsetOutOfRange { .. }  => "offset out of range",
+            Error::DateOutOfRange           => "date out of range",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::OffsetOutOfRange { offset }  => write!(f, "offset of {} seconds is out of range", offset),
+            Error::DateOutOfRange               => write!(f, "date is out of range"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use time::{Date, Month, Time};
+    use test_helpers::ltt;
+
+    /// New York in 2021: EDT from 07:00 UTC on 14 March to 06:00 UTC on
+    /// 7 November.
+    fn new_york() -> TZData {
+        TZData::from_timespans(ltt(-18000, false, "EST"), vec![
+            (1_615_705_200, ltt(-14400, true, "EDT")),
+            (1_636_264_800, ltt(-18000, false, "EST")),
+        ])
+    }
+
+    fn local(month: Month, day: u8, hour: u8, minute: u8) -> PrimitiveDateTime {
+        PrimitiveDateTime::new(Date::from_calendar_date(2021, month, day).unwrap(),
+                               Time::from_hms(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn utc() {
+        let tz = new_york();
+        let instant = local(Month::July, 4, 16, 0).assume_utc();
+        assert_eq!(utc_offset_at(&tz, instant).unwrap(), UtcOffset::from_hms(-4, 0, 0).unwrap());
+
+        let converted = to_offset_datetime(&tz, instant).unwrap();
+        assert_eq!(converted.hour(), 12);
+        assert_eq!(converted, instant);
+    }
+
+    #[test]
+    fn local_times() {
+        let tz = new_york();
+        assert_eq!(from_local(&tz, local(Month::March, 14, 2, 30)).unwrap(), LocalResult::None);
+
+        let noon = from_local(&tz, local(Month::July, 4, 12, 0)).unwrap();
+        assert_eq!(noon, LocalResult::Single(local(Month::July, 4, 16, 0).assume_utc()));
+
+        match from_local(&tz, local(Month::November, 7, 1, 30)).unwrap() {
+            LocalResult::Ambiguous(earlier, later) => {
+                assert_eq!(earlier.offset().whole_hours(), -4);
+                assert_eq!(later.offset().whole_hours(), -5);
+            },
+            result => panic!("expected an ambiguous result, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn footer() {
+        let tz = ::parse(include_bytes!("../../tests/fixtures/london-slim.tzif").to_vec()).unwrap();
+        let at = |month, day, hour, minute| {
+            PrimitiveDateTime::new(Date::from_calendar_date(2024, month, day).unwrap(), Time::from_hms(hour, minute, 0).unwrap())
+        };
+
+        let summer = at(Month::July, 1, 12, 0).assume_utc();
+        assert_eq!(utc_offset_at(&tz, summer).unwrap(), UtcOffset::from_hms(1, 0, 0).unwrap());
+        assert_eq!(utc_offset_at(&tz, at(Month::January, 1, 12, 0).assume_utc()).unwrap(), UtcOffset::UTC);
+
+        assert_eq!(from_local(&tz, at(Month::March, 31, 1, 30)).unwrap(), LocalResult::None);
+        match from_local(&tz, at(Month::October, 27, 1, 30)).unwrap() {
+            LocalResult::Ambiguous(earlier, later) => {
+                assert_eq!(earlier.offset().whole_hours(), 1);
+                assert_eq!(later.offset().whole_hours(), 0);
+            },
+            result => panic!("expected an ambiguous result, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! Comparing zones
+//!
+//! Two files can describe exactly the same local times while being
+//! different byte-for-byte: `zic -b fat` lists transitions that `-b slim`
+//! leaves to the footer, and can repeat local time types or add
+//! transitions that don’t change anything. So rather than comparing how two
+//! zones are encoded, `diff` compares what they say the local time is over
+//! a range of time, and reports the differences as a list of changes.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use super::{Indicators, LeapSecond, LocalTimeType, Result, TZData};
+use posix::{self, PosixTz};
+use tree;
+
+
+/// The furthest apart a removed and an added transition can be and still
+/// count as the same transition having moved: half a year, which is enough
+/// for a daylight-saving time rule to move by a few weeks or months.
+const MAX_MOVE: u64 = 183 * 24 * 60 * 60;
+
+/// The start of 2000, which is where the cycle of 400 years that footers
+/// get compared over starts.
+const GREGORIAN_CYCLE_START: i64 = 946_684_800;
+
+/// The length of 400 years of the Gregorian calendar, after which the days
+/// of the week and the leap years repeat.
+const GREGORIAN_CYCLE: i64 = 146_097 * 24 * 60 * 60;
+
+
+/// A difference between two versions of a zone.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+
+    /// The local time type in effect at the start of the range changed.
+    Initial {
+
+        /// The local time type that the old version started in.
+        old: LocalTimeType,
+
+        /// The local time type that the new version starts in.
+        new: LocalTimeType,
+    },
+
+    /// There is a new transition at a time that didn’t have one.
+    TransitionAdded {
+
+        /// The Unix timestamp of the new transition.
+        timestamp: i64,
+
+        /// The local time type that the new transition changes to.
+        local_time_type: LocalTimeType,
+    },
+
+    /// A transition is no longer there.
+    TransitionRemoved {
+
+        /// The Unix timestamp of the transition that’s gone.
+        timestamp: i64,
+
+        /// The local time type that the transition used to change to.
+        local_time_type: LocalTimeType,
+    },
+
+    /// A transition to the same local time type happens at a different
+    /// time, such as when the start of daylight-saving time moves.
+    TransitionMoved {
+
+        /// The Unix timestamp that the transition used to happen at.
+        old_timestamp: i64,
+
+        /// The Unix timestamp that the transition happens at now.
+        new_timestamp: i64,
+
+        /// The local time type that the transition changes to.
+        local_time_type: LocalTimeType,
+    },
+
+    /// A transition happens at the same time, but changes to a local time
+    /// type with a different offset, abbreviation, or DST flag.
+    TransitionChanged {
+
+        /// The Unix timestamp of the transition.
+        timestamp: i64,
+
+        /// The local time type that the transition used to change to.
+        old: LocalTimeType,
+
+        /// The local time type that the transition changes to now.
+        new: LocalTimeType,
+    },
+
+    /// The rule in the footer for times after the last transition changed,
+    /// so it gives different local times.
+    Footer {
+
+        /// The old version’s footer, if it had one.
+        old: Option<String>,
+
+        /// The new version’s footer, if it has one.
+        new: Option<String>,
+    },
+
+    /// There is a new leap second.
+    LeapSecondAdded(LeapSecond),
+
+    /// A leap second is no longer there.
+    LeapSecondRemoved(LeapSecond),
+
+    /// A leap second has a different correction.
+    LeapSecondChanged {
+
+        /// The leap second as it was.
+        old: LeapSecond,
+
+        /// The leap second as it is now.
+        new: LeapSecond,
+    },
+}
+
+
+/// The local times that a zone describes over a range of time, with its
+/// footer extrapolated and any transitions that don’t change anything left
+/// out.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Timeline {
+
+    /// The local time type in effect at the start of the range.
+    pub(crate) initial: LocalTimeType,
+
+    /// Each transition after the start of the range and before its end,
+    /// along with the local time type it changes to.
+    pub(crate) transitions: Vec<(i64, LocalTimeType)>,
+}
+
+/// Works out the timeline of a zone over a range of time.
+///
+/// Local time types are compared by their abbreviation, offset, and DST
+/// flag alone, as the indicators don’t affect the local time.
+pub(crate) fn timeline(tz: &TZData, range: Range<i64>) -> Result<Timeline> {
+    let footer = footer(tz)?;
+
+    // The footer takes over after the last transition, or straight away if
+    // there are no transitions at all.
+    let footer_start = tz.transitions.last().map(|t| t.timestamp);
+
+    let initial = plain(&tz.local_time_type_at(range.start));
+    let mut current = initial.clone();
+    let mut transitions = Vec::new();
+
+    let listed = tz.transitions.iter()
+                   .filter(|t| range.start < t.timestamp && t.timestamp < range.end)
+                   .map(|t| (t.timestamp, plain(&tz.local_time_types[t.local_time_type])));
+
+    let extrapolated = match footer {
+        Some(ref footer) => {
+            let start = footer_start.map_or(range.start, |s| s.max(range.start)).saturating_add(1);
+            footer.checked_transitions(start .. range.end)?.into_iter().map(|(t, ltt)| (t, plain(ltt))).collect()
+        },
+        None => Vec::new(),
+    };
+
+    for (timestamp, ltt) in listed.chain(extrapolated) {
+        if ltt != current {
+            current = ltt.clone();
+            transitions.push((timestamp, ltt));
+        }
+    }
+
+    Ok(Timeline { initial, transitions })
+}
+
+fn footer(tz: &TZData) -> Result<Option<PosixTz>> {
+    match tz.footer {
+        Some(ref footer)  => posix::parse(footer).map(Some),
+        None              => Ok(None),
+    }
+}
+
+/// Whether two footers give the same local time at every instant, such as
+/// `EST5EDT` and `EST5EDT,M3.2.0,M11.1.0/2`. Rules repeat every 400 years
+/// along with the Gregorian calendar, so one cycle is all that needs
+/// checking.
+fn same_footer(old: Option<&PosixTz>, new: Option<&PosixTz>) -> bool {
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new))  => (old, new),
+        (old, new)              => return old.is_none() && new.is_none(),
+    };
+
+    let cycle = GREGORIAN_CYCLE_START .. GREGORIAN_CYCLE_START + GREGORIAN_CYCLE;
+    let transitions = |rule: &PosixTz| -> Vec<_> {
+        rule.transitions(cycle.clone()).into_iter().map(|(t, ltt)| (t, plain(ltt))).collect()
+    };
+
+    plain(old.local_time_type_at(cycle.start)) == plain(new.local_time_type_at(cycle.start))
+        && transitions(old) == transitions(new)
+}
+
+/// Returns a copy of a local time type without its indicators.
+fn plain(ltt: &LocalTimeType) -> LocalTimeType {
+    LocalTimeType { indicators: Indicators::UNSPECIFIED, .. ltt.clone() }
+}
+
+
+/// Compares two versions of a zone over a range of Unix timestamps,
+/// returning the differences between them in order.
+///
+/// Transitions are compared after extrapolating each zone’s footer over the
+/// range, so it’s an error for a footer with a daylight-saving time rule to
+/// cover more than `posix::MAX_YEARS` years of it. Footers are compared by
+/// the local times they give, rather than by how they’re written.
+pub fn diff(old: &TZData, new: &TZData, range: Range<i64>) -> Result<Vec<Change>> {
+    let old_timeline = timeline(old, range.clone())?;
+    let new_timeline = timeline(new, range.clone())?;
+    let mut changes = Vec::new();
+
+    if old_timeline.initial != new_timeline.initial {
+        changes.push(Change::Initial { old: old_timeline.initial, new: new_timeline.initial });
+    }
+
+    changes.extend(diff_transitions(old_timeline.transitions, new_timeline.transitions));
+    changes.extend(diff_leap_seconds(old, new, &range));
+
+    if !same_footer(footer(old)?.as_ref(), footer(new)?.as_ref()) {
+        changes.push(Change::Footer { old: old.footer.clone(), new: new.footer.clone() });
+    }
+
+    Ok(changes)
+}
+
+/// Matches up the transitions of two timelines, pairing any that were
+/// removed with any nearby ones to the same type that were added.
+fn diff_transitions(old: Vec<(i64, LocalTimeType)>, new: Vec<(i64, LocalTimeType)>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+
+    loop {
+        let ordering = match (old.peek(), new.peek()) {
+            (Some(o), Some(n))  => o.0.cmp(&n.0),
+            (Some(_), None)     => Ordering::Less,
+            (None, Some(_))     => Ordering::Greater,
+            (None, None)        => break,
+        };
+
+        match ordering {
+            Ordering::Less     => removed.extend(old.next()),
+            Ordering::Greater  => added.extend(new.next()),
+            Ordering::Equal    => {
+                let (timestamp, old_type) = old.next().unwrap();
+                let (_, new_type) = new.next().unwrap();
+                if old_type != new_type {
+                    changes.push(Change::TransitionChanged { timestamp, old: old_type, new: new_type });
+                }
+            },
+        }
+    }
+
+    let mut added: Vec<Option<(i64, LocalTimeType)>> = added.into_iter().map(Some).collect();
+    for (timestamp, ltt) in removed {
+        let nearest = added.iter().enumerate()
+                           .filter_map(|(i, a)| a.as_ref().map(|a| (i, a)))
+                           .filter(|&(_, a)| a.1 == ltt && a.0.abs_diff(timestamp) <= MAX_MOVE)
+                           .min_by_key(|&(_, a)| a.0.abs_diff(timestamp))
+                           .map(|(i, _)| i);
+
+        match nearest.and_then(|i| added[i].take()) {
+            Some((new_timestamp, _)) => {
+                changes.push(Change::TransitionMoved { old_timestamp: timestamp, new_timestamp, local_time_type: ltt });
+            },
+            None => {
+                changes.push(Change::TransitionRemoved { timestamp, local_time_type: ltt });
+            },
+        }
+    }
+
+    for (timestamp, ltt) in added.into_iter().flatten() {
+        changes.push(Change::TransitionAdded { timestamp, local_time_type: ltt });
+    }
+
+    changes.sort_by_key(|c| match *c {
+        Change::TransitionAdded { timestamp, .. }
+      | Change::TransitionRemoved { timestamp, .. }
+      | Change::TransitionChanged { timestamp, .. }
+      | Change::TransitionMoved { old_timestamp: timestamp, .. }  => timestamp,
+        _                                                         => 0,
+    });
+
+    changes
+}
+
+/// Compares the leap seconds of two zones that fall inside the range.
+fn diff_leap_seconds(old: &TZData, new: &TZData, range: &Range<i64>) -> Vec<Change> {
+    let old: Vec<_> = old.leap_seconds.iter().filter(|ls| range.contains(&ls.timestamp)).collect();
+    let new: Vec<_> = new.leap_seconds.iter().filter(|ls| range.contains(&ls.timestamp)).collect();
+    let mut changes = Vec::new();
+
+    for &&ls in &old {
+        match new.iter().find(|n| n.timestamp == ls.timestamp) {
+            Some(&&n) if n != ls  => changes.push(Change::LeapSecondChanged { old: ls, new: n }),
+            Some(_)               => {},
+            None                  => changes.push(Change::LeapSecondRemoved(ls)),
+        }
+    }
+
+    for &&ls in &new {
+        if !old.iter().any(|o| o.timestamp == ls.timestamp) {
+            changes.push(Change::LeapSecondAdded(ls));
+        }
+    }
+
+    changes
+}
+
+
+/// The differences between two zoneinfo trees.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TreeDiff {
+
+    /// The names of zones that are only in the new tree.
+    pub added: Vec<String>,
+
+    /// The names of zones that are only in the old tree.
+    pub removed: Vec<String>,
+
+    /// The names of zones in both trees that differ, along with how.
+    pub changed: Vec<(String, Vec<Change>)>,
+
+    /// The names of zones in both trees that couldn’t be compared, as one
+    /// of their files couldn’t be read or parsed, along with the error.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Compares every zone in two zoneinfo trees, such as the ones from two
+/// releases of tzdata, over a range of Unix timestamps.
+///
+/// A zone that can’t be compared gets listed in `failed`, and the rest of
+/// the zones still get compared. It’s only an error if the trees
+/// themselves can’t be read.
+pub fn diff_trees<P: AsRef<Path>, Q: AsRef<Path>>(old_root: P, new_root: Q, range: Range<i64>) -> Result<TreeDiff> {
+    let old_root = old_root.as_ref();
+    let new_root = new_root.as_ref();
+    let old_names = tree::zone_names(old_root)?;
+    let new_names = tree::zone_names(new_root)?;
+    let mut tree_diff = TreeDiff::default();
+
+    for name in &old_names {
+        if new_names.binary_search(name).is_err() {
+            tree_diff.removed.push(name.clone());
+            continue;
+        }
+
+        let compare = || -> Result<Vec<Change>> {
+            let old = super::parse_named(fs::read(old_root.join(name))?, name)?;
+            let new = super::parse_named(fs::read(new_root.join(name))?, name)?;
+            diff(&old, &new, range.clone())
+        };
+
+        match compare() {
+            Ok(ref changes) if changes.is_empty()  => {},
+            Ok(changes)                            => tree_diff.changed.push((name.clone(), changes)),
+            Err(e)                                 => tree_diff.failed.push((name.clone(), e.to_string())),
+        }
+    }
+
+    for name in new_names {
+        if old_names.binary_search(&name).is_err() {
+            tree_diff.added.push(name);
+        }
+    }
+
+    Ok(tree_diff)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::{empty_tree, ltt};
+    use writer;
+
+    /// Europe/Berlin from 1980 onwards, as `zic -b slim` would write it:
+    /// no transitions after 1996, where the footer takes over.
+    fn slim() -> TZData {
+        let mut tz = TZData::from_timespans(ltt(3600, false, "CET"), vec![
+            (  828_234_000, ltt(7200, true, "CEST")),
+            (  846_378_000, ltt(3600, false, "CET")),
+        ]);
+
+        tz.footer = Some("CET-1CEST,M3.5.0,M10.5.0/3".to_owned());
+        tz
+    }
+
+    /// The same, as `zic -b fat` would write it, with the transitions for
+    /// 1997 listed and a redundant one in the middle of 1997.
+    fn fat() -> TZData {
+        let mut tz = TZData::from_timespans(ltt(3600, false, "CET"), vec![
+            (  828_234_000, ltt(7200, true, "CEST")),
+            (  846_378_000, ltt(3600, false, "CET")),
+            (  859_683_600, LocalTimeType { indicators: Indicators { is_std: Some(true), is_ut: Some(true) }, .. ltt(7200, true, "CEST") }),
+            (  860_000_000, ltt(7200, true, "CEST")),
+            (  877_827_600, ltt(3600, false, "CET")),
+        ]);
+
+        tz.footer = Some("CET-1CEST,M3.5.0,M10.5.0/3".to_owned());
+        tz
+    }
+
+    /// 1995 to 2000.
+    const RANGE: Range<i64> = 788_918_400 .. 946_684_800;
+
+    #[test]
+    fn slim_and_fat() {
+        assert_eq!(diff(&slim(), &fat(), RANGE).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn footer_extrapolation() {
+        let timeline = timeline(&slim(), RANGE).unwrap();
+        assert_eq!(timeline.initial.name, "CET");
+        assert_eq!(timeline.transitions.len(), 8);
+
+        // 1999-03-28 01:00 UTC
+        assert_eq!(timeline.transitions[6], (922_582_800, ltt(7200, true, "CEST")));
+    }
+
+    #[test]
+    fn rule_changes() {
+        let mut new = slim();
+        new.footer = Some("CET-1CEST,M3.5.0,M11.1.0/3".to_owned());
+
+        let changes = diff(&slim(), &new, RANGE).unwrap();
+        assert_eq!(changes.len(), 4);
+
+        // 1997-10-26 01:00 UTC to 1997-11-02 01:00 UTC
+        assert_eq!(changes[0], Change::TransitionMoved {
+            old_timestamp: 877_827_600,
+            new_timestamp: 878_432_400,
+            local_time_type: ltt(3600, false, "CET"),
+        });
+
+        assert_eq!(changes[3], Change::Footer { old: slim().footer, new: new.footer });
+    }
+
+    #[test]
+    fn offset_changes() {
+        let mut new = slim();
+        new.local_time_types[1].name = "CEMT".to_owned();
+        new.footer = None;
+
+        let changes = diff(&slim(), &new, 788_918_400 .. 852_076_800).unwrap();
+        assert_eq!(changes[0], Change::TransitionChanged {
+            timestamp: 828_234_000,
+            old: ltt(7200, true, "CEST"),
+            new: ltt(7200, true, "CEMT"),
+        });
+        assert_eq!(changes[1], Change::Footer { old: slim().footer, new: None });
+    }
+
+    #[test]
+    fn footers_compared_by_behaviour() {
+        let old = TZData::from_posix_tz_rule("EST5EDT,0/0,J365/25").unwrap();
+        let new = TZData::from_posix_tz_rule("EST5EDT,J1/0,J365/25").unwrap();
+        assert_eq!(diff(&old, &new, RANGE).unwrap(), vec![]);
+
+        let new = TZData::from_posix_tz_rule("EST5EDT,J2/0,J365/25").unwrap();
+        assert_eq!(diff(&old, &new, RANGE).unwrap().last(), Some(&Change::Footer { old: old.footer.clone(), new: new.footer.clone() }));
+    }
+
+    #[test]
+    fn wide_ranges() {
+        assert!(diff(&slim(), &fat(), i64::MIN .. i64::MAX).is_err());
+
+        let old = TZData::from_timespans(ltt(0, false, "GMT"), vec![ (i64::MIN + 1, ltt(3600, true, "BST")) ]);
+        let new = TZData::from_timespans(ltt(0, false, "GMT"), vec![ (i64::MAX - 1, ltt(3600, true, "BST")) ]);
+        assert_eq!(diff(&old, &new, i64::MIN .. i64::MAX).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn leap_seconds() {
+        let mut new = slim();
+        new.leap_seconds.push(LeapSecond { timestamp: 915_148_800, leap_second_count: 22 });
+
+        let changes = diff(&slim(), &new, RANGE).unwrap();
+        assert_eq!(changes, vec![ Change::LeapSecondAdded(new.leap_seconds[0]) ]);
+    }
+
+    #[test]
+    fn trees() {
+        let (old_root, new_root) = (empty_tree("diff", "trees-old"), empty_tree("diff", "trees-new"));
+        let mut changed = slim();
+        changed.footer = None;
+
+        for &(root, ref berlin) in &[ (&old_root, slim()), (&new_root, changed) ] {
+            fs::create_dir_all(root.join("Europe")).unwrap();
+            fs::write(root.join("Europe/Berlin"), writer::to_bytes(berlin).unwrap()).unwrap();
+            fs::write(root.join("Europe/Busingen"), writer::to_bytes(&slim()).unwrap()).unwrap();
+        }
+
+        fs::write(old_root.join("Europe/Paris"), writer::to_bytes(&slim()).unwrap()).unwrap();
+        fs::write(new_root.join("Europe/Zurich"), writer::to_bytes(&slim()).unwrap()).unwrap();
+
+        // A zone that can’t be parsed doesn’t stop the others being compared.
+        fs::write(new_root.join("Europe/Busingen"), b"TZif2").unwrap();
+
+        let tree_diff = diff_trees(&old_root, &new_root, RANGE).unwrap();
+        assert_eq!(tree_diff.removed, vec![ "Europe/Paris".to_owned() ]);
+        assert_eq!(tree_diff.added, vec![ "Europe/Zurich".to_owned() ]);
+        assert_eq!(tree_diff.changed.len(), 1);
+        assert_eq!(tree_diff.changed[0].0, "Europe/Berlin");
+        assert_eq!(tree_diff.failed.len(), 1);
+        assert_eq!(tree_diff.failed[0].0, "Europe/Busingen");
+
+        fs::remove_dir_all(&old_root).unwrap();
+        fs::remove_dir_all(&new_root).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Writing bundles of compiled zone files
+//!
+//! This module only uses the standard library, as it also gets included
+//! into the build script.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use tree;
+
+
+/// The magic number at the start of every bundle.
+pub(crate) const MAGIC: &[u8; 4] = b"TZbn";
+
+/// The length of the magic number and the count of zones.
+pub(crate) const HEADER_LENGTH: usize = 8;
+
+/// The length of each zone’s entry in the index.
+pub(crate) const ENTRY_LENGTH: usize = 14;
+
+
+/// A set of compiled zone files, to be written out as one blob for
+/// embedding into a binary.
+///
+/// Build scripts can use this to bundle their own choice of zones, and
+/// read them back at runtime with `Archive`:
+///
+/// ```no_run
+/// use zoneinfo_compiled::embedded::Bundle;
+///
+/// let mut bundle = Bundle::from_zoneinfo("/usr/share/zoneinfo").unwrap();
+/// bundle.select(|name| name.starts_with("Europe/"));
+/// bundle.write_to("zones.bin").unwrap();
+/// ```
+///
+/// Zones with identical contents, such as links, only have their data
+/// stored once.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Bundle {
+    zones: Vec<(String, Vec<u8>)>,
+}
+
+impl Bundle {
+
+    /// Creates a new, empty bundle.
+    pub fn new() -> Bundle {
+        Bundle::default()
+    }
+
+    /// Creates a bundle containing every zone in the tree of compiled files
+    /// under the given root, such as `/usr/share/zoneinfo`.
+    pub fn from_zoneinfo<P: AsRef<Path>>(root: P) -> io::Result<Bundle> {
+        let root = root.as_ref();
+        let mut bundle = Bundle::new();
+
+        for name in tree::zone_names(root)? {
+            let data = fs::read(root.join(&name))?;
+            bundle.add(name, data);
+        }
+
+        Ok(bundle)
+    }
+
+    /// Adds a zone’s compiled data to the bundle, replacing any zone that
+    /// was already added with the same name.
+    pub fn add(&mut self, name: String, data: Vec<u8>) {
+        self.zones.retain(|z| z.0 != name);
+        self.zones.push((name, data));
+    }
+
+    /// Keeps only the zones whose names match the given function, to
+    /// control the size of the bundle.
+    pub fn select<F: FnMut(&str) -> bool>(&mut self, mut filter: F) {
+        self.zones.retain(|z| filter(&z.0));
+    }
+
+    /// Returns the names of the zones in the bundle.
+    pub fn zone_names(&self) -> Vec<&str> {
+        self.zones.iter().map(|z| &*z.0).collect()
+    }
+
+    /// Serialises the bundle into a blob, returning an error if a name or
+    /// the blob is too large for the index to refer to.
+    ///
+    /// The blob starts with a magic number and the number of zones, then an
+    /// index entry for each zone in order of name -- the start and length
+    /// of its name, as a `u32` and a `u16`, and the start and length of its
+    /// data as `u32`s -- followed by the names and then the data. Every
+    /// start is counted from the start of the blob, and all numbers are
+    /// big-endian. As the entries all have the same size and are sorted, a
+    /// zone can be found without reading the whole index.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut zones: Vec<(&String, &Vec<u8>)> = self.zones.iter().map(|z| (&z.0, &z.1)).collect();
+        zones.sort_by_key(|z| z.0);
+
+        let names_start = HEADER_LENGTH + ENTRY_LENGTH * zones.len();
+        let data_start = names_start + zones.iter().map(|z| z.0.len()).sum::<usize>();
+
+        let mut index = Vec::with_capacity(ENTRY_LENGTH * zones.len());
+        let mut names = Vec::with_capacity(data_start - names_start);
+        let mut data: Vec<u8> = Vec::new();
+        let mut stored: Vec<(usize, &[u8])> = Vec::new();
+
+        for &(name, contents) in &zones {
+            let start = match stored.iter().find(|s| s.1 == &contents[..]) {
+                Some(s) => s.0,
+                None    => {
+                    let start = data_start + data.len();
+                    data.extend_from_slice(contents);
+                    stored.push((start, contents));
+                    start
+                },
+            };
+
+            index.extend_from_slice(&to_u32(names_start + names.len())?.to_be_bytes());
+            index.extend_from_slice(&u16::try_from(name.len()).map_err(|_| too_large("zone name"))?.to_be_bytes());
+            index.extend_from_slice(&to_u32(start)?.to_be_bytes());
+            index.extend_from_slice(&to_u32(contents.len())?.to_be_bytes());
+            names.extend_from_slice(name.as_bytes());
+        }
+
+        to_u32(data_start + data.len())?;
+
+        let mut bytes = Vec::with_capacity(data_start + data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&to_u32(zones.len())?.to_be_bytes());
+        bytes.extend_from_slice(&index);
+        bytes.extend_from_slice(&names);
+        bytes.extend_from_slice(&data);
+        Ok(bytes)
+    }
+
+    /// Serialises the bundle into a blob, and writes it to a file.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes()?)
+    }
+}
+
+
+/// Converts a position or length in a blob to the `u32` that the index
+/// stores it as.
+fn to_u32(n: usize) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| too_large("bundle"))
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{} is too large to be indexed", what))
+}
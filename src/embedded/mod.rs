@@ -0,0 +1,270 @@
+//! Zone data embedded into the binary at build time
+//!
+//! With the `embedded` feature enabled, the build script bundles a tree of
+//! compiled zone files into the library itself, so programs can look up
+//! zones without needing a zoneinfo tree at runtime. Two environment
+//! variables control what gets bundled:
+//!
+//! - `ZONEINFO_COMPILED_EMBED_DIR` is the root of the tree to bundle,
+//!   defaulting to `/usr/share/zoneinfo`. The build fails if it can’t be
+//!   read or has no zones in it;
+//! - `ZONEINFO_COMPILED_EMBED_ZONES` is a comma-separated list of the zones
+//!   to include, such as `Europe/,America/New_York`, where a name ending in
+//!   a slash includes every zone under that directory. Every zone gets
+//!   included if it’s not set.
+//!
+//! Programs that want to pick their zones in code can use a `Bundle` in
+//! their own build script instead, and read the result with `Archive`.
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use std::cmp::Ordering;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::result;
+
+use super::{Result, TZData};
+
+mod bundle;
+pub use self::bundle::Bundle;
+
+
+/// The bundle written by the build script.
+static EMBEDDED: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/zoneinfo.bin"));
+
+
+/// Parses and interprets the embedded data for the zone with the given
+/// name, returning an error if it wasn’t bundled.
+///
+/// This only looks at the index entries it needs to find the zone, rather
+/// than reading the whole index each time.
+pub fn get(name: &str) -> Result<TZData> {
+    let data = find(EMBEDDED, name)?.ok_or_else(|| Error::UnknownZone(name.to_owned()))?;
+    super::parse_named(data.to_vec(), name)
+}
+
+/// Returns the names of every zone that was bundled.
+pub fn zone_names() -> Vec<&'static str> {
+    Archive::new(EMBEDDED).map(|a| a.zone_names()).unwrap_or_default()
+}
+
+
+/// A read-only view of a blob written by a `Bundle`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Archive<'a> {
+
+    /// The name of each zone, along with its compiled data, in order of
+    /// name.
+    entries: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> Archive<'a> {
+
+    /// Reads the index of a blob, returning an error if it is invalid or
+    /// points outside of the blob.
+    pub fn new(blob: &'a [u8]) -> Result<Archive<'a>> {
+        let count = read_header(blob)?;
+        let entries = (0 .. count).map(|i| entry(blob, i))
+                                  .collect::<Result<Vec<_>>>()?;
+
+        if entries.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err(Box::new(Error::InvalidIndex));
+        }
+
+        Ok(Archive { entries })
+    }
+
+    /// Returns the names of every zone in the blob.
+    pub fn zone_names(&self) -> Vec<&'a str> {
+        self.entries.iter().map(|e| e.0).collect()
+    }
+
+    /// Returns the compiled data for the zone with the given name, or `None`
+    /// if there is no such zone.
+    pub fn zone_data(&self, name: &str) -> Option<&'a [u8]> {
+        self.entries.binary_search_by(|e| e.0.cmp(name)).ok().map(|i| self.entries[i].1)
+    }
+
+    /// Parses and interprets the compiled data for the zone with the given
+    /// name, returning an error if there is no such zone.
+    pub fn get(&self, name: &str) -> Result<TZData> {
+        let data = self.zone_data(name)
+                       .ok_or_else(|| Error::UnknownZone(name.to_owned()))?;
+
+        super::parse_named(data.to_vec(), name)
+    }
+}
+
+
+/// Checks a blob’s magic number, returning the number of zones in it.
+fn read_header(blob: &[u8]) -> Result<usize> {
+    let mut cursor = Cursor::new(blob);
+
+    let mut magic = [0u8; 4];
+    cursor.read_exact(&mut magic)?;
+    if magic != *bundle::MAGIC {
+        return Err(Box::new(Error::InvalidMagicNumber));
+    }
+
+    Ok(cursor.read_u32::<BigEndian>()? as usize)
+}
+
+/// Reads the index entry with the given number, returning the zone’s name
+/// and data.
+fn entry(blob: &[u8], number: usize) -> Result<(&str, &[u8])> {
+    let position = number.checked_mul(bundle::ENTRY_LENGTH)
+                         .and_then(|p| p.checked_add(bundle::HEADER_LENGTH))
+                         .ok_or(Error::InvalidIndex)?;
+
+    let mut cursor = Cursor::new(blob.get(position ..).ok_or(Error::InvalidIndex)?);
+    let name_start   = cursor.read_u32::<BigEndian>()? as usize;
+    let name_length  = cursor.read_u16::<BigEndian>()? as usize;
+    let start        = cursor.read_u32::<BigEndian>()? as usize;
+    let length       = cursor.read_u32::<BigEndian>()? as usize;
+
+    let slice = |start: usize, length: usize| {
+        start.checked_add(length)
+             .and_then(|end| blob.get(start .. end))
+             .ok_or(Error::InvalidIndex)
+    };
+
+    let name = slice(name_start, name_length)?;
+    let contents = slice(start, length)?;
+    Ok((::std::str::from_utf8(name)?, contents))
+}
+
+/// Finds the data for the zone with the given name in a blob, searching
+/// its sorted index without reading every entry.
+fn find<'a>(blob: &'a [u8], name: &str) -> Result<Option<&'a [u8]>> {
+    let (mut low, mut high) = (0, read_header(blob)?);
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let (entry_name, contents) = entry(blob, middle)?;
+
+        match entry_name.cmp(name) {
+            Ordering::Less     => low = middle + 1,
+            Ordering::Greater  => high = middle,
+            Ordering::Equal    => return Ok(Some(contents)),
+        }
+    }
+
+    Ok(None)
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Error {
+
+    /// The error when the blob doesn’t start with a bundle’s magic number.
+    InvalidMagicNumber,
+
+    /// The error when an index entry refers to data outside of the blob.
+    InvalidIndex,
+
+    /// The error when there is no zone with the requested name.
+    UnknownZone(String),
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidMagicNumber  => "invalid magic number",
+            Error::InvalidIndex        => "invalid index",
+            Error::UnknownZone(_)      => "unknown zone",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidMagicNumber     => write!(f, "invalid magic number"),
+            Error::InvalidIndex           => write!(f, "invalid bundle index"),
+            Error::UnknownZone(ref name)  => write!(f, "unknown zone {:?}", name),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::EST;
+
+    #[test]
+    fn round_trip() {
+        let mut bundle = Bundle::new();
+        bundle.add("EST".to_owned(), EST.to_vec());
+        bundle.add("Etc/GMT+5".to_owned(), EST.to_vec());
+        bundle.add("Mars/Olympus_Mons".to_owned(), EST.to_vec());
+        bundle.select(|name| !name.starts_with("Mars/"));
+
+        let bytes = bundle.to_bytes().unwrap();
+        let archive = Archive::new(&bytes).unwrap();
+        assert_eq!(archive.zone_names(), vec![ "EST", "Etc/GMT+5" ]);
+        assert_eq!(archive.zone_data("Etc/GMT+5"), Some(EST));
+        assert_eq!(archive.get("EST").unwrap().local_time_types[0].offset, -18000);
+
+        // The data for both zones is only stored once.
+        assert_eq!(bytes.len(), 8 + 14 * 2 + "EST".len() + "Etc/GMT+5".len() + EST.len());
+    }
+
+    #[test]
+    fn sorted_index() {
+        let mut bundle = Bundle::new();
+        for name in &[ "Pacific/Auckland", "America/New_York", "Europe/Paris", "Asia/Tokyo", "EST" ] {
+            bundle.add(name.to_string(), name.as_bytes().to_vec());
+        }
+
+        let bytes = bundle.to_bytes().unwrap();
+        let archive = Archive::new(&bytes).unwrap();
+        assert_eq!(archive.zone_names(), vec![ "America/New_York", "Asia/Tokyo", "EST", "Europe/Paris", "Pacific/Auckland" ]);
+
+        for name in archive.zone_names() {
+            assert_eq!(find(&bytes, name).unwrap(), Some(name.as_bytes()));
+        }
+        assert_eq!(find(&bytes, "Europe/London").unwrap(), None);
+        assert_eq!(find(&bytes, "Zulu").unwrap(), None);
+    }
+
+    #[test]
+    fn too_large() {
+        let mut bundle = Bundle::new();
+        bundle.add("X".repeat(70_000), EST.to_vec());
+        assert!(bundle.to_bytes().is_err());
+    }
+
+    #[test]
+    fn unknown_zone() {
+        let bytes = Bundle::new().to_bytes().unwrap();
+        assert!(Archive::new(&bytes).unwrap().get("EST").is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut bundle = Bundle::new();
+        bundle.add("EST".to_owned(), EST.to_vec());
+
+        let mut bytes = bundle.to_bytes().unwrap();
+        bytes.pop();
+        assert!(Archive::new(&bytes).is_err());
+        assert!(find(&bytes, "EST").is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut bundle = Bundle::new();
+        bundle.add("EST".to_owned(), EST.to_vec());
+        let bytes = bundle.to_bytes().unwrap();
+
+        // The name’s start, then the data’s start and length.
+        for &position in &[ 8, 14, 18 ] {
+            let mut bytes = bytes.clone();
+            bytes[position .. position + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+            assert!(Archive::new(&bytes).is_err());
+            assert!(find(&bytes, "EST").is_err());
+        }
+    }
+}
@@ -9,54 +9,179 @@
 
 use std::borrow::Cow;
 use std::convert::AsRef;
-use std::path::Path;
-use std::sync::Arc;
+use std::fmt;
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Component, Path, PathBuf};
+use std::result;
+use std::sync::{Arc, RwLock};
 
 extern crate byteorder;
-extern crate datetime;
-use datetime::zone::{TimeZone, TimeType, TimeZoneSource, FixedTimespan};
-use datetime::zone::runtime::{OwnedTimeZone, OwnedFixedTimespanSet};
+#[cfg(feature = "datetime")] extern crate datetime;
+#[cfg(feature = "chrono")] extern crate chrono;
+#[cfg(feature = "time")] extern crate time;
+#[cfg(feature = "jiff")] extern crate jiff;
+#[cfg(all(feature = "watch", target_os = "linux"))] extern crate inotify;
+#[cfg(all(feature = "watch", target_os = "linux"))] extern crate libc;
 
 pub mod parser;
 pub use parser::Result;
 
+pub mod aliases;
+pub mod android;
+pub mod builder;
+pub mod cache;
+pub mod compat;
+pub mod diff;
+pub mod posix;
+pub mod tab;
+pub mod version;
+pub mod writer;
+pub mod zi;
 
-pub trait CompiledData {
-    fn parse(input: Vec<u8>) -> Result<TimeZone>;
+pub use builder::TZDataBuilder;
+pub use cache::ZoneCache;
 
-    fn from_file<P: AsRef<Path>>(path: P) -> Result<TimeZone> {
-        use std::io::{Read, BufReader};
-        use std::fs::File;
+#[cfg(feature = "datetime")]
+pub use compat::datetime::CompiledData;
 
-        let f = File::open(path)?;
-        let mut r = BufReader::new(f);
-        let mut contents: Vec<u8> = Vec::new();
+#[cfg(feature = "embedded")]
+pub mod embedded;
 
-        r.read_to_end(&mut contents)?;
-        let tz = Self::parse(contents)?;
-        Ok(tz)
+mod tree;
+
+#[cfg(test)]
+mod test_helpers;
+
+
+/// Reads a zoneinfo file into a timezone data structure.
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<TZData> {
+    parse(read_file(path)?)
+}
+
+/// Reads a file from inside the zoneinfo tree under the given root into a
+/// timezone data structure, naming it after its path relative to the root.
+///
+/// For example, reading `/usr/share/zoneinfo/Europe/Paris` with a root of
+/// `/usr/share/zoneinfo` produces a zone named "Europe/Paris". Symbolic
+/// links, such as `/etc/localtime`, get followed into the tree if the path
+/// itself isn’t inside it.
+pub fn from_file_named<R: AsRef<Path>, P: AsRef<Path>>(root: R, path: P) -> Result<TZData> {
+    let name = zone_name(root, &path)?;
+    parse_named(read_file(path)?, &name)
+}
+
+/// Reads the zone with the given name, such as "Europe/Paris", from the
+/// zoneinfo tree under the given root.
+pub fn from_zoneinfo<R: AsRef<Path>>(root: R, name: &str) -> Result<TZData> {
+    parse_named(read_file(zoneinfo_path(root, name)?)?, name)
+}
+
+
+/// Reads the entire contents of a file.
+fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    use std::io::{Read, BufReader};
+    use std::fs::File;
+
+    let f = File::open(path)?;
+    let mut r = BufReader::new(f);
+    let mut contents: Vec<u8> = Vec::new();
+
+    r.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Returns the path of the zone with the given name in the zoneinfo tree
+/// under the given root, returning an error if the name would lead outside
+/// of the tree.
+fn zoneinfo_path<R: AsRef<Path>>(root: R, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        let message = format!("invalid zone name {:?}", name);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
     }
+
+    Ok(root.as_ref().join(relative))
 }
 
-impl CompiledData for TimeZone {
-    fn parse(input: Vec<u8>) -> Result<TimeZone> {
-        let data = parse(input)?;
-        let arc = Arc::new(data.time_zone);
-        let tz = TimeZone(TimeZoneSource::Runtime(arc));
-        Ok(tz)
+/// Works out the name of the zone stored at a path inside the zoneinfo tree
+/// under the given root, such as "Europe/Paris", returning an error if the
+/// path isn’t inside the tree.
+///
+/// If the path isn’t inside the tree as given, both paths get resolved --
+/// following any symbolic links, such as `/etc/localtime` -- and compared
+/// again.
+pub fn zone_name<R: AsRef<Path>, P: AsRef<Path>>(root: R, path: P) -> Result<String> {
+    let (root, path) = (root.as_ref(), path.as_ref());
+
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => {
+            let (root, path) = (root.canonicalize()?, path.canonicalize()?);
+            match path.strip_prefix(&root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => {
+                    let message = format!("{} is not inside {}", path.display(), root.display());
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+                },
+            }
+        },
+    };
+
+    let components: Option<Vec<&str>> = relative.components().map(|c| match c {
+        Component::Normal(part) => part.to_str(),
+        _                       => None,
+    }).collect();
+
+    match components {
+        Some(ref parts) if !parts.is_empty() => Ok(parts.join("/")),
+        _ => {
+            let message = format!("{} does not name a zone", relative.display());
+            Err(io::Error::new(io::ErrorKind::InvalidInput, message).into())
+        },
     }
 }
 
 
 /// Parsed, interpreted contents of a zoneinfo file.
-#[derive(PartialEq, Debug)]
+///
+/// The first local time type is the one in effect before the first
+/// transition (or forever, if there are no transitions), and every
+/// transition refers to one of the local time types by its index.
+#[derive(PartialEq, Debug, Clone)]
 pub struct TZData {
 
-    /// Vector of transitions that are described in this data.
-    pub time_zone: OwnedTimeZone,
+    /// The name of this zone in the zoneinfo database, such as
+    /// "Europe/Paris", if it was loaded by name.
+    pub name: Option<String>,
+
+    /// Vector of local time types that the transitions refer to.
+    pub local_time_types: Vec<LocalTimeType>,
+
+    /// Vector of transitions that are described in this data, in order.
+    pub transitions: Vec<Transition>,
 
     /// Vector of leap seconds that are described in this data.
     pub leap_seconds: Vec<LeapSecond>,
+
+    /// The POSIX TZ string describing the rules in effect after the last
+    /// transition, such as `CET-1CEST,M3.5.0,M10.5.0/3`, if there is one.
+    pub footer: Option<String>,
+
+    /// The footer, parsed the first time it’s needed.
+    footer_rule: FooterCache,
+}
+
+
+/// A point in time at which the local time type changes.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Transition {
+
+    /// Unix timestamp at which the transition occurs.
+    pub timestamp: i64,
+
+    /// Index of the local time type in effect from this transition onwards.
+    pub local_time_type: usize,
 }
 
 
@@ -65,7 +190,7 @@ pub struct TZData {
 pub struct LeapSecond {
 
     /// Unix timestamp at which a leap second occurs.
-    pub timestamp: i32,
+    pub timestamp: i64,
 
     /// Number of leap seconds to be added.
     pub leap_second_count: i32,
@@ -86,23 +211,612 @@ pub struct LocalTimeType {
     /// Whether to set DST.
     pub is_dst: bool,
 
-    /// The current 'type' of time.
-    pub transition_type: TimeType,
+    /// The standard/wall and UT/local indicators that the file gave for
+    /// this local time type, if any.
+    pub indicators: Indicators,
 }
 
 impl LocalTimeType {
 
-    /// Convert this set of fields into datetime’s `FixedTimespan`
-    /// representation.
+    /// Returns the clock that transitions into this local time type were
+    /// given in terms of, treating unspecified indicators as false.
+    pub fn transition_type(&self) -> TimeType {
+        TimeType::from(self.indicators)
+    }
+}
+
+
+/// The two indicators a file can give for a local time type, which record
+/// how the transition times in the zone’s source were specified.
+///
+/// A file can leave out either set of indicators altogether, and files
+/// written by `zic -b slim` usually do, so each one is `None` when the file
+/// doesn’t say.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Indicators {
+
+    /// Whether transition times were given in standard time, rather than
+    /// wall clock time.
+    pub is_std: Option<bool>,
+
+    /// Whether transition times were given in Universal Time, rather than
+    /// local time.
+    pub is_ut: Option<bool>,
+}
+
+impl Indicators {
+
+    /// The indicators for a local time type that has neither of them.
+    pub const UNSPECIFIED: Indicators = Indicators { is_std: None, is_ut: None };
+}
+
+/// Combines the two indicators to get the type of a transition. A UT
+/// indicator that’s set overrides the standard one, as UT times are also
+/// standard times.
+impl From<Indicators> for TimeType {
+    fn from(indicators: Indicators) -> TimeType {
+        match (indicators.is_std.unwrap_or(false), indicators.is_ut.unwrap_or(false)) {
+            (_,     true)   => TimeType::UTC,
+            (true,  _)      => TimeType::Standard,
+            (false, false)  => TimeType::Wall,
+        }
+    }
+}
+
+/// Specifies both indicators for a type of transition.
+impl From<TimeType> for Indicators {
+    fn from(time_type: TimeType) -> Indicators {
+        Indicators {
+            is_std:  Some(time_type != TimeType::Wall),
+            is_ut:   Some(time_type == TimeType::UTC),
+        }
+    }
+}
+
+
+/// The clock that a time is given in terms of.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TimeType {
+
+    /// Wall clock time, including any daylight-saving offset.
+    Wall,
+
+    /// Standard time, without any daylight-saving offset.
+    Standard,
+
+    /// Universal Time.
+    UTC,
+}
+
+
+/// The years that transitions get listed for when building a zone out of a
+/// POSIX TZ string.
+const DEFAULT_POSIX_YEARS: RangeInclusive<i64> = 1970 ..= 2037;
+
+/// The furthest from UTC that any zone’s offset can be, in seconds.
+const MAX_OFFSET: i64 = 26 * 60 * 60;
+
+impl TZData {
+
+    /// Builds zone data out of the local time type in effect before the
+    /// first transition, and each transition along with the type it changes
+    /// to, giving equal types the same index.
+    fn from_timespans(first: LocalTimeType, rest: Vec<(i64, LocalTimeType)>) -> TZData {
+        let mut local_time_types = vec![ first ];
+
+        let transitions = rest.into_iter().map(|(timestamp, ltt)| {
+            let local_time_type = match local_time_types.iter().position(|t| *t == ltt) {
+                Some(index) => index,
+                None => {
+                    local_time_types.push(ltt);
+                    local_time_types.len() - 1
+                },
+            };
+
+            Transition { timestamp, local_time_type }
+        }).collect();
+
+        TZData {
+            name: None,
+            local_time_types,
+            transitions,
+            leap_seconds: Vec::new(),
+            footer: None,
+            footer_rule: FooterCache::default(),
+        }
+    }
+
+    /// Builds zone data out of a POSIX TZ string, such as the value of the
+    /// `TZ` environment variable, listing the transitions its rule produces
+    /// from 1970 to 2037, as `zic -b fat` does.
+    ///
+    /// The string is kept as the zone’s footer, so code that extrapolates
+    /// past the last transition carries on following the rule.
+    pub fn from_posix_tz(tz: &str) -> Result<TZData> {
+        TZData::from_posix_tz_years(tz, DEFAULT_POSIX_YEARS)
+    }
+
+    /// Builds zone data out of a POSIX TZ string, listing the transitions
+    /// its rule produces in the given years, and keeping the string as the
+    /// zone’s footer.
+    ///
+    /// The zone starts out in the local time type in effect at the start of
+    /// the first year. If the range is empty, this is the same as
+    /// `from_posix_tz_rule`.
+    pub fn from_posix_tz_years(tz: &str, years: RangeInclusive<i64>) -> Result<TZData> {
+        if years.is_empty() {
+            return TZData::from_posix_tz_rule(tz);
+        }
+
+        let rule = posix::parse(tz)?;
+        let start = zi::days_from_civil(*years.start(), 1, 1).saturating_mul(86400);
+        let end = zi::days_from_civil(years.end().saturating_add(1), 1, 1).saturating_mul(86400);
+        let rest = rule.transitions(start .. end).into_iter().map(|(t, ltt)| (t, ltt.clone())).collect();
+
+        let mut data = TZData::from_timespans(rule.local_time_type_at(start).clone(), rest);
+        data.footer = Some(tz.to_owned());
+        Ok(data)
+    }
+
+    /// Builds zone data out of a POSIX TZ string without listing any
+    /// transitions, keeping it as a pure rule for code that reads the
+    /// footer. The zone starts out in standard time, followed by the
+    /// daylight-saving time type if there is one.
+    pub fn from_posix_tz_rule(tz: &str) -> Result<TZData> {
+        let rule = posix::parse(tz)?;
+        let mut local_time_types = vec![ rule.std ];
+        local_time_types.extend(rule.dst.map(|dst| dst.local_time_type));
+
+        Ok(TZData {
+            name: None,
+            local_time_types,
+            transitions: Vec::new(),
+            leap_seconds: Vec::new(),
+            footer: Some(tz.to_owned()),
+            footer_rule: FooterCache::default(),
+        })
+    }
+
+    /// Builds zone data for a zone that’s always at the given offset from
+    /// UTC in seconds, with the given abbreviation, such as “EST”.
+    ///
+    /// The result is the same as reading a file that zic compiled for such
+    /// a zone: one local time type, no transitions, and a footer that
+    /// describes it. Returns an error if the abbreviation is empty, or the
+    /// offset is further from UTC than any zone’s can be.
+    pub fn fixed(offset: i64, abbreviation: &str) -> Result<TZData> {
+        let mut builder = TZDataBuilder::new();
+        builder.add_type(abbreviation, offset, false);
+
+        let footer = posix::format_fixed(&builder.build()?.local_time_types[0]);
+        builder.footer(&footer).build()
+    }
+
+    /// Builds zone data for one of the zones in the `Etc` area with a fixed
+    /// offset, such as “Etc/GMT+5” or “GMT-14”, named after it. Returns
+    /// `None` if the name isn’t one of them.
+    ///
+    /// These names follow the POSIX convention, so their signs are the
+    /// other way around: “Etc/GMT+5” is five hours *behind* UTC, and is
+    /// abbreviated “-05”. Only “GMT”, “GMT0”, “GMT+0” and “GMT-0” may leave
+    /// out the sign, and are abbreviated “GMT”; the others run from
+    /// “Etc/GMT-14” to “Etc/GMT+12”, as in tzdata.
+    pub fn from_etc_gmt(name: &str) -> Option<TZData> {
+        let suffix = name.strip_prefix("Etc/").unwrap_or(name).strip_prefix("GMT")?;
+
+        let (sign, hours) = match suffix.as_bytes().first() {
+            None                => (1, "0"),
+            Some(b'+')          => (-1, &suffix[1..]),
+            Some(b'-')          => (1, &suffix[1..]),
+            Some(_)             => (1, suffix),
+        };
+
+        if hours.is_empty() || hours.len() > 2 || !hours.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        // Only zero may be written without a sign, and the zones themselves
+        // only go from “Etc/GMT-14” to “Etc/GMT+12”.
+        let hours: i64 = hours.parse().ok()?;
+        let unsigned = !suffix.starts_with('+') && !suffix.starts_with('-');
+        if (unsigned && (hours != 0 || suffix.len() > 1)) || (sign < 0 && hours > 12) || (sign > 0 && hours > 14) {
+            return None;
+        }
+
+        let abbreviation = match hours {
+            0 => "GMT".to_owned(),
+            _ => format!("{}{:02}", if sign > 0 { '+' } else { '-' }, hours),
+        };
+
+        let mut data = TZData::fixed(sign * hours * 3600, &abbreviation).ok()?;
+        data.set_name(name);
+        Some(data)
+    }
+
+    /// Whether the local time in this zone never changes, so it always has
+    /// the same offset, abbreviation, and DST flag, both in its transitions
+    /// and in its footer.
+    pub fn is_fixed(&self) -> bool {
+        let first = match self.local_time_types.first() {
+            Some(first) => first,
+            None        => return false,
+        };
+
+        let same = |ltt: &LocalTimeType| ltt.name == first.name && ltt.offset == first.offset && ltt.is_dst == first.is_dst;
+        let transitions_fixed = self.transitions.iter().all(|t| self.local_time_types.get(t.local_time_type).map_or(false, same));
+
+        let footer_fixed = match self.footer {
+            Some(ref footer) => posix::parse(footer).ok().map_or(false, |rule| rule.dst.is_none() && same(&rule.std)),
+            None             => true,
+        };
+
+        transitions_fixed && footer_fixed
+    }
+
+    /// Sets the name of this zone, such as "Europe/Paris".
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_owned());
+    }
+
+    /// Returns the local time type in effect at the given Unix timestamp.
+    ///
+    /// After the last transition, or at any time if there are none, this
+    /// follows the rule in the footer, if there’s one that can be parsed.
+    /// Its local time types get borrowed from this zone’s when they have
+    /// the same abbreviation, offset, and DST flag, and are returned owned
+    /// otherwise.
+    pub fn local_time_type_at(&self, timestamp: i64) -> Cow<'_, LocalTimeType> {
+        let footer = self.footer_rule();
+        let source = self.source_at(footer.as_deref(), timestamp);
+        self.owned_type(footer.as_deref(), source)
+    }
+
+    /// Returns the local time types in effect at the given local time, given
+    /// as the number of seconds since midnight on 1970-01-01 as shown on a
+    /// clock in this zone.
+    ///
+    /// A local time may have been skipped over when the clocks went forward,
+    /// or happened twice when they went back, so there may be no types or
+    /// two of them. As with `local_time_type_at`, the footer’s rule is
+    /// followed after the last transition.
+    pub fn resolve_local(&self, local: i64) -> LocalResult<Cow<'_, LocalTimeType>> {
+        let footer = self.footer_rule();
+        self.resolve_local_sources(footer.as_deref(), local).map(|source| self.owned_type(footer.as_deref(), source))
+    }
+
+    /// Removes transitions that don’t change the offset, abbreviation, or DST
+    /// flag, merges identical local time types, and removes unused ones,
+    /// without changing the local times this zone describes.
+    ///
+    /// The last transition is kept if there’s a footer, as it marks where
+    /// the footer takes over, and the local time type in effect before the
+    /// first transition stays first.
+    pub fn normalize(&mut self) {
+        if self.local_time_types.is_empty() {
+            return;
+        }
+
+        let footer_start = if self.footer.is_some() { self.transitions.len().checked_sub(1) } else { None };
+        let mut current = 0;
+        let mut rest = Vec::with_capacity(self.transitions.len());
+
+        for (i, t) in self.transitions.iter().enumerate() {
+            let (before, after) = (&self.local_time_types[current], &self.local_time_types[t.local_time_type]);
+            let changes = before.name != after.name || before.offset != after.offset || before.is_dst != after.is_dst;
+
+            if changes || footer_start == Some(i) {
+                current = t.local_time_type;
+                rest.push((t.timestamp, after.clone()));
+            }
+        }
+
+        let normalized = TZData::from_timespans(self.local_time_types[0].clone(), rest);
+        self.local_time_types = normalized.local_time_types;
+        self.transitions = normalized.transitions;
+    }
+
+    /// Cuts this zone down to the transitions from `start` up to, but not
+    /// including, `end`, in the same way as `zic -r @start/@end`.
+    ///
+    /// Local time is unspecified outside of the range, so if `start` is
+    /// later than `i64::MIN`, the zone starts out in a local time type
+    /// abbreviated “-00” and changes to the type in effect at `start` then.
+    /// If `end` is earlier than `i64::MAX`, the footer’s transitions up to
+    /// `end` are listed, the footer is removed, and the zone changes back to
+    /// “-00” at `end`. Leap seconds outside the range are removed too.
+    ///
+    /// If there are no transitions and `start` is `i64::MIN`, the footer’s
+    /// transitions are only listed from 1970, as with `from_posix_tz`.
+    ///
+    /// Returns an error if `start` isn’t earlier than `end`, if the footer
+    /// can’t be parsed, or if its rule would have to be extrapolated over
+    /// more than `posix::MAX_YEARS` years.
+    pub fn truncate(&mut self, start: i64, end: i64) -> Result<()> {
+        if start >= end {
+            let message = format!("empty range of time {} to {}", start, end);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+        }
+
+        let footer = match self.footer {
+            Some(ref footer)  => Some(posix::parse(footer)?),
+            None              => None,
+        };
+
+        let cut_start = start > i64::MIN;
+        let cut_end = end < i64::MAX;
+        let footer_start = self.transitions.last().map(|t| t.timestamp);
+        let type_at_start = self.local_time_type_at(start).into_owned();
+
+        let mut timespans: Vec<_> = self.transitions.iter()
+            .map(|t| (t.timestamp, self.local_time_types[t.local_time_type].clone()))
+            .collect();
+
+        match footer {
+            Some(ref footer) if cut_end => {
+                // A footer that’s in effect from the start of time only has
+                // its transitions listed from 1970, as with `from_posix_tz`,
+                // unless the zone gets cut before then.
+                let from = match footer_start {
+                    Some(s)             => s.max(start).saturating_add(1),
+                    None if cut_start   => start,
+                    None => {
+                        let from = zi::days_from_civil(*DEFAULT_POSIX_YEARS.start(), 1, 1) * 86400;
+                        let (first, at_from) = (&self.local_time_types[0], footer.local_time_type_at(from));
+                        if (&first.name, first.offset, first.is_dst) != (&at_from.name, at_from.offset, at_from.is_dst) {
+                            timespans.push((from, at_from.clone()));
+                        }
+
+                        from
+                    },
+                };
+
+                timespans.extend(footer.checked_transitions(from .. end)?.into_iter().map(|(t, ltt)| (t, ltt.clone())));
+            },
+            _ => {},
+        }
+
+        let unspecified = LocalTimeType {
+            name:        "-00".to_owned(),
+            offset:      0,
+            is_dst:      false,
+            indicators:  Indicators::UNSPECIFIED,
+        };
+
+        let first = if cut_start { unspecified.clone() } else { self.local_time_types[0].clone() };
+        let mut rest = Vec::with_capacity(timespans.len() + 2);
+        if cut_start {
+            rest.push((start, type_at_start));
+        }
+
+        rest.extend(timespans.into_iter().filter(|&(t, _)| (!cut_start || t > start) && t < end));
+        if cut_end {
+            rest.push((end, unspecified));
+            self.footer = None;
+        }
+
+        let truncated = TZData::from_timespans(first, rest);
+        self.local_time_types = truncated.local_time_types;
+        self.transitions = truncated.transitions;
+        self.leap_seconds.retain(|ls| start <= ls.timestamp && ls.timestamp < end);
+        Ok(())
+    }
+
+    /// Whether this zone and another give the same offset, abbreviation, and
+    /// DST flag at every instant in the given range of Unix timestamps.
+    ///
+    /// Unlike comparing them with `==`, this only looks at what the zones
+    /// say the local time is: transitions past the last listed one are
+    /// worked out from the footer, and transitions that don’t change
+    /// anything, duplicate local time types, and indicators don’t count. So
+    /// the same zone compiled with `zic -b slim` and `zic -b fat` is
+    /// equivalent, even though the files are different.
     ///
-    /// It doesn’t actually contain any `'static` data, but if the lifetime is
-    /// not specified, Rust ties its lifetime to `self`, when they’re actually
-    /// completely unrelated.
-    fn to_fixed_timespan(&self) -> FixedTimespan<'static> {
-        FixedTimespan {
-            offset: self.offset,
-            is_dst: self.is_dst,
-            name: Cow::Owned(self.name.clone()),
+    /// Working out a footer’s transitions takes time, so the range can span
+    /// at most `posix::MAX_YEARS` years of a footer with a daylight-saving
+    /// time rule. Returns an error if it’s any longer, or if either footer
+    /// can’t be parsed.
+    pub fn equivalent(&self, other: &TZData, range: Range<i64>) -> Result<bool> {
+        if range.start >= range.end {
+            return Ok(true);
+        }
+
+        Ok(diff::timeline(self, range.clone())? == diff::timeline(other, range)?)
+    }
+
+    /// Returns the index of the span of time in effect at the given
+    /// timestamp, where the span before the first transition is 0 and the
+    /// one following each transition is one more.
+    fn span_at(&self, timestamp: i64) -> usize {
+        self.transitions.partition_point(|t| t.timestamp <= timestamp)
+    }
+
+    /// Returns the index of the local time type in effect during a span.
+    fn span_type(&self, span: usize) -> usize {
+        match span {
+            0 => 0,
+            i => self.transitions[i - 1].local_time_type,
+        }
+    }
+
+    /// Parses the footer, ignoring it if it can’t be parsed, as lookups
+    /// can’t fail. The result is kept until the footer changes, so lookups
+    /// don’t parse it every time.
+    pub(crate) fn footer_rule(&self) -> Option<Arc<posix::PosixTz>> {
+        let footer = self.footer.as_ref()?;
+        if let Some((ref parsed, ref rule)) = *self.footer_rule.0.read().unwrap_or_else(|e| e.into_inner()) {
+            if parsed == footer {
+                return rule.clone();
+            }
+        }
+
+        let rule = posix::parse(footer).ok().map(Arc::new);
+        *self.footer_rule.0.write().unwrap_or_else(|e| e.into_inner()) = Some((footer.clone(), rule.clone()));
+        rule
+    }
+
+    /// Returns the first timestamp the footer’s rule applies to, which is
+    /// just after the last transition, or any time if there are none.
+    fn footer_start(&self) -> i64 {
+        self.transitions.last().map_or(i64::MIN, |t| t.timestamp.saturating_add(1))
+    }
+
+    /// Returns where the local time type in effect at the given timestamp
+    /// comes from, given the parsed footer.
+    pub(crate) fn source_at(&self, footer: Option<&posix::PosixTz>, timestamp: i64) -> TypeSource {
+        match footer {
+            Some(footer) if timestamp >= self.footer_start() => {
+                self.footer_source(footer, footer.local_time_type_at(timestamp).is_dst)
+            },
+            _ => TypeSource::Table(self.span_type(self.span_at(timestamp))),
+        }
+    }
+
+    /// Returns where one of the footer’s local time types comes from,
+    /// preferring one of this zone’s local time types that’s the same.
+    fn footer_source(&self, footer: &posix::PosixTz, is_dst: bool) -> TypeSource {
+        let ltt = footer.local_time_type(is_dst);
+        match self.local_time_types.iter().position(|t| t.name == ltt.name && t.offset == ltt.offset && t.is_dst == ltt.is_dst) {
+            Some(index) => TypeSource::Table(index),
+            None        => TypeSource::Footer(is_dst),
+        }
+    }
+
+    /// Returns the local time type that a source refers to.
+    pub(crate) fn source_type<'a>(&'a self, footer: Option<&'a posix::PosixTz>, source: TypeSource) -> &'a LocalTimeType {
+        match source {
+            TypeSource::Table(index)   => &self.local_time_types[index],
+            TypeSource::Footer(is_dst) => footer.expect("footer sources only come from a footer").local_time_type(is_dst),
+        }
+    }
+
+    /// Returns the local time type that a source refers to, borrowing it if
+    /// it’s one of this zone’s own.
+    fn owned_type(&self, footer: Option<&posix::PosixTz>, source: TypeSource) -> Cow<'_, LocalTimeType> {
+        match source {
+            TypeSource::Table(index)  => Cow::Borrowed(&self.local_time_types[index]),
+            TypeSource::Footer(_)     => Cow::Owned(self.source_type(footer, source).clone()),
+        }
+    }
+
+    /// Returns the spans of time in effect between two timestamps, as the
+    /// timestamp each starts at along with where its local time type comes
+    /// from. The first span is given as starting at `i64::MIN`, and the
+    /// footer’s rule takes over after the last transition.
+    fn spans(&self, footer: Option<&posix::PosixTz>, from: i64, to: i64) -> Vec<(i64, TypeSource)> {
+        let footer_start = self.footer_start();
+        let mut spans = Vec::new();
+
+        if footer.is_none() || from < footer_start {
+            let (first, last) = (self.span_at(from), self.span_at(to));
+            for span in first ..= last {
+                let start = if span == first { i64::MIN } else { self.transitions[span - 1].timestamp };
+                spans.push((start, TypeSource::Table(self.span_type(span))));
+            }
+        }
+
+        match footer {
+            Some(footer) if to >= footer_start => {
+                let begin = from.max(footer_start);
+                let start = if spans.is_empty() { i64::MIN } else { footer_start };
+                spans.push((start, self.footer_source(footer, footer.local_time_type_at(begin).is_dst)));
+
+                for (timestamp, ltt) in footer.transitions(begin.saturating_add(1) .. to.saturating_add(1)) {
+                    spans.push((timestamp, self.footer_source(footer, ltt.is_dst)));
+                }
+            },
+            _ => {},
+        }
+
+        spans
+    }
+
+    /// Returns where the local time types in effect at the given local time
+    /// come from.
+    pub(crate) fn resolve_local_sources(&self, footer: Option<&posix::PosixTz>, local: i64) -> LocalResult<TypeSource> {
+        // Only spans in effect within a day or so of the local time can
+        // contain it, as no offset is any further from UTC than that.
+        let spans = self.spans(footer, local.saturating_sub(MAX_OFFSET), local.saturating_add(MAX_OFFSET));
+
+        let mut matches = (0 .. spans.len()).filter(|&i| {
+            let timestamp = local.saturating_sub(self.source_type(footer, spans[i].1).offset);
+            let starts_before = spans[i].0 <= timestamp;
+            let ends_after = i + 1 == spans.len() || timestamp < spans[i + 1].0;
+            starts_before && ends_after
+        });
+
+        match (matches.next(), matches.next_back()) {
+            (None, _)                 => LocalResult::None,
+            (Some(i), None)           => LocalResult::Single(spans[i].1),
+            (Some(i), Some(j))        => LocalResult::Ambiguous(spans[i].1, spans[j].1),
+        }
+    }
+}
+
+
+/// The footer of a zone along with what parsing it gave, kept so that it
+/// only gets parsed again once the footer changes. It doesn’t count towards
+/// whether two zones are equal, as it only depends on the footer.
+#[derive(Default)]
+struct FooterCache(RwLock<Option<(String, Option<Arc<posix::PosixTz>>)>>);
+
+impl Clone for FooterCache {
+    fn clone(&self) -> FooterCache {
+        FooterCache(RwLock::new(self.0.read().unwrap_or_else(|e| e.into_inner()).clone()))
+    }
+}
+
+impl PartialEq for FooterCache {
+    fn eq(&self, _other: &FooterCache) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for FooterCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        f.write_str("FooterCache")
+    }
+}
+
+
+/// Where the local time type in effect at some time comes from: either one
+/// of the zone’s own local time types, or one from its footer that none of
+/// them match.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum TypeSource {
+
+    /// The zone’s local time type with this index.
+    Table(usize),
+
+    /// The footer’s daylight-saving time type if this is true, or its
+    /// standard time type if not.
+    Footer(bool),
+}
+
+
+/// The result of looking up the local time types in effect at a local time.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum LocalResult<T> {
+
+    /// The local time never happened, as it was skipped over when the
+    /// clocks went forward.
+    None,
+
+    /// The local time happened once.
+    Single(T),
+
+    /// The local time happened twice, as the clocks went back; the earlier
+    /// of the two comes first.
+    Ambiguous(T, T),
+}
+
+impl<T> LocalResult<T> {
+
+    /// Converts the value or values in this result using a function.
+    pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> LocalResult<U> {
+        match self {
+            LocalResult::None              => LocalResult::None,
+            LocalResult::Single(a)         => LocalResult::Single(f(a)),
+            LocalResult::Ambiguous(a, b)   => LocalResult::Ambiguous(f(a), f(b)),
         }
     }
 }
@@ -114,15 +828,31 @@ pub fn parse(input: Vec<u8>) -> Result<TZData> {
     cook(tz)
 }
 
+/// Parses a series of bytes into a timezone data structure with the given
+/// name, such as "Europe/Paris".
+pub fn parse_named(input: Vec<u8>, name: &str) -> Result<TZData> {
+    let mut data = parse(input)?;
+    data.set_name(name);
+    Ok(data)
+}
+
 
 /// Interpret a set of internal time zone data.
+///
+/// This checks that the data makes sense, so the lookups on the result
+/// can’t fail: there has to be at least one local time type, every
+/// transition has to refer to one that exists, and transitions have to be
+/// in order.
 pub fn cook(tz: parser::TZData) -> Result<TZData> {
-    let mut transitions = Vec::with_capacity(tz.header.num_transitions as usize);
-    let mut local_time_types = Vec::with_capacity(tz.header.num_local_time_types as usize);
+    let mut transitions = Vec::with_capacity(tz.transitions.len());
+    let mut local_time_types = Vec::with_capacity(tz.time_info.len());
+
+    if tz.time_info.is_empty() {
+        return Err(Box::new(parser::Error::NoLocalTimeTypes));
+    }
 
     // First, build up a list of local time types...
-    for i in 0 .. tz.header.num_local_time_types as usize {
-        let ltt = &tz.time_info[i];
+    for (i, ltt) in tz.time_info.iter().enumerate() {
 
         // Isolate the relevant bytes by the index of the start of the
         // string and the next available null char
@@ -132,28 +862,38 @@ pub fn cook(tz: parser::TZData) -> Result<TZData> {
                                    .take_while(|&c| c != 0)
                                    .collect();
 
-        // (TODO: move to ‘copied’ when the library supports that Rust version)
-        let std_flag = tz.standard_flags.get(i).cloned().unwrap_or_default() != 0;
-        let gmt_flag = tz.gmt_flags.get(i).cloned().unwrap_or_default() != 0;
+        // The flags are stored in two separate sets, either of which can
+        // be missing, so they can only be combined after the entire file
+        // has been read.
+        let indicators = Indicators {
+            is_std:  tz.standard_flags.get(i).map(|&f| f != 0),
+            is_ut:   tz.gmt_flags.get(i).map(|&f| f != 0),
+        };
 
         let info = LocalTimeType {
-            name:             String::from_utf8(name_bytes)?,
-            offset:           ltt.offset as i64,
-            is_dst:           ltt.is_dst != 0,
-            transition_type:  flags_to_transition_type(std_flag, gmt_flag),
+            name:        String::from_utf8(name_bytes)?,
+            offset:      ltt.offset as i64,
+            is_dst:      ltt.is_dst != 0,
+            indicators,
         };
 
         local_time_types.push(info);
     }
 
     // ...then, link each transition with the time type it refers to.
-    for i in 0 .. tz.header.num_transitions as usize {
-        let t = &tz.transitions[i];
-        let ltt = local_time_types[t.local_time_type_index as usize].clone();
-        let timespan = ltt.to_fixed_timespan();
+    for (i, t) in tz.transitions.iter().enumerate() {
+        if usize::from(t.local_time_type_index) >= local_time_types.len() {
+            return Err(Box::new(parser::Error::InvalidLocalTimeTypeIndex { index: t.local_time_type_index }));
+        }
+
+        if i > 0 && tz.transitions[i - 1].timestamp >= t.timestamp {
+            return Err(Box::new(parser::Error::UnsortedTransitions));
+        }
 
-        let transition = (t.timestamp as i64, timespan);
-        transitions.push(transition);
+        transitions.push(Transition {
+            timestamp:        t.timestamp,
+            local_time_type:  t.local_time_type_index as usize,
+        });
     }
 
     let mut leap_seconds = Vec::new();
@@ -166,48 +906,274 @@ pub fn cook(tz: parser::TZData) -> Result<TZData> {
         leap_seconds.push(leap_second);
     }
 
-    // The `OwnedTimeZone` struct *requires* there to be at least one
-    // transition. If there aren’t any in the file, we need to reach back into
-    // the structure to get the *base* offset time, as it won’t be in the
-    // transitions list.
+    Ok(TZData {
+        name: None,
+        local_time_types,
+        transitions,
+        leap_seconds,
+        footer: tz.footer,
+        footer_rule: FooterCache::default(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::ltt;
+
+    #[test]
+    fn zone_names_from_paths() {
+        assert_eq!(zone_name("/usr/share/zoneinfo", "/usr/share/zoneinfo/Europe/Paris").unwrap(), "Europe/Paris");
+        assert_eq!(zone_name("/usr/share/zoneinfo/", "/usr/share/zoneinfo/UTC").unwrap(), "UTC");
+        assert!(zone_name("/nonexistent/zoneinfo", "/nonexistent/elsewhere/UTC").is_err());
+        assert!(zone_name("/usr/share/zoneinfo", "/usr/share/zoneinfo").is_err());
+    }
+
+    #[test]
+    fn zoneinfo_paths() {
+        assert_eq!(zoneinfo_path("/zoneinfo", "Europe/Paris").unwrap(), Path::new("/zoneinfo/Europe/Paris"));
+        assert!(zoneinfo_path("/zoneinfo", "../../etc/passwd").is_err());
+        assert!(zoneinfo_path("/zoneinfo", "/etc/passwd").is_err());
+        assert!(zoneinfo_path("/zoneinfo", "").is_err());
+    }
 
-    if transitions.is_empty() {
-        let time_zone = OwnedTimeZone {
-            name: None,
-            fixed_timespans: OwnedFixedTimespanSet {
-                first: local_time_types[0].to_fixed_timespan(),
-                rest: Vec::new(),
+    #[test]
+    fn indicators() {
+        assert_eq!(TimeType::from(Indicators::UNSPECIFIED), TimeType::Wall);
+        assert_eq!(TimeType::from(Indicators { is_std: Some(true), is_ut: None }), TimeType::Standard);
+        assert_eq!(TimeType::from(Indicators { is_std: Some(false), is_ut: Some(true) }), TimeType::UTC);
+
+        for &time_type in &[ TimeType::Wall, TimeType::Standard, TimeType::UTC ] {
+            assert_eq!(TimeType::from(Indicators::from(time_type)), time_type);
+        }
+    }
+
+    fn raw(transitions: Vec<parser::TransitionData>, types: usize) -> parser::TZData {
+        parser::TZData {
+            header: parser::Header {
+                version: b'2', num_gmt_flags: 0, num_standard_flags: 0, num_leap_seconds: 0,
+                num_transitions: transitions.len() as u32, num_local_time_types: types as u32, num_abbr_chars: 4,
             },
-        };
+            transitions,
+            time_info: vec![ parser::LocalTimeTypeData { offset: 0, is_dst: 0, name_offset: 0 }; types ],
+            leap_seconds: Vec::new(),
+            strings: b"UTC\0".to_vec(),
+            standard_flags: Vec::new(),
+            gmt_flags: Vec::new(),
+            footer: None,
+        }
+    }
 
-        Ok(TZData { time_zone, leap_seconds })
+    #[test]
+    fn cook_validates() {
+        let transition = |timestamp, local_time_type_index| parser::TransitionData { timestamp, local_time_type_index };
+
+        assert!(cook(raw(vec![], 1)).is_ok());
+        assert!(cook(raw(vec![ transition(0, 0), transition(1, 1) ], 2)).is_ok());
+
+        assert!(cook(raw(vec![], 0)).is_err());
+        assert!(cook(raw(vec![ transition(0, 1) ], 1)).is_err());
+        assert!(cook(raw(vec![ transition(1, 0), transition(0, 1) ], 2)).is_err());
+        assert!(cook(raw(vec![ transition(0, 0), transition(0, 1) ], 2)).is_err());
     }
-    else {
-        // We don’t care about the timestamp that the first transition happens
-        // at: we assume it to have been in effect forever.
-        let first = transitions.remove(0);
-        let time_zone = OwnedTimeZone {
-            name: None,
-            fixed_timespans: OwnedFixedTimespanSet {
-                first: first.1,
-                rest: transitions,
-            }
-        };
 
-        Ok(TZData { time_zone, leap_seconds })
+    #[test]
+    fn equivalent_encodings() {
+        // Europe/London in 2021, with DST from 28 March to 31 October
+        let mut slim = TZData::from_timespans(ltt(0, false, "GMT"), vec![
+            (1_616_893_200, ltt(3600, true, "BST")),
+        ]);
+        slim.footer = Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned());
+
+        let mut fat = TZData::from_timespans(ltt(0, false, "GMT"), vec![
+            (1_616_893_200, ltt(3600, true, "BST")),
+            (1_625_097_600, ltt(3600, true, "BST")),
+            (1_635_642_000, LocalTimeType { indicators: Indicators::from(TimeType::UTC), .. ltt(0, false, "GMT") }),
+        ]);
+        fat.footer = slim.footer.clone();
+
+        let year = 1_609_459_200 .. 1_640_995_200;
+        assert!(slim != fat);
+        assert!(slim.equivalent(&fat, year.clone()).unwrap());
+
+        fat.transitions.pop();
+        assert!(slim.equivalent(&fat, year.clone()).unwrap());
+
+        fat.footer = None;
+        assert!(!slim.equivalent(&fat, year.clone()).unwrap());
+        assert!(slim.equivalent(&fat, 1_609_459_200 .. 1_635_642_000).unwrap());
+
+        fat.footer = Some("invalid".to_owned());
+        assert!(slim.equivalent(&fat, year).is_err());
+
+        // Over all of time, the footer’s rule would have to be extrapolated
+        // forever, but a fixed one never changes.
+        assert!(slim.equivalent(&slim, i64::MIN .. i64::MAX).is_err());
+        let utc = TZData::fixed(0, "UTC").unwrap();
+        assert!(utc.equivalent(&utc, i64::MIN .. i64::MAX).unwrap());
     }
-}
 
+    #[test]
+    fn truncate() {
+        let mut tz = TZData::from_timespans(ltt(561, false, "LMT"), vec![
+            (-2_486_592_561, ltt(0, false, "WET")),
+            (  354_675_600, ltt(7200, true, "CEST")),
+            (  370_400_400, ltt(3600, false, "CET")),
+        ]);
+        tz.footer = Some("CET-1CEST,M3.5.0,M10.5.0/3".to_owned());
 
-/// Combine the two flags to get the type of this transition.
-///
-/// The transition type is stored as two separate flags in the data file. The
-/// first set comes completely before the second, so these can only be
-/// combined after the entire file has been read.
-fn flags_to_transition_type(standard: bool, gmt: bool) -> TimeType {
-    match (standard, gmt) {
-        (_,     true)   => TimeType::UTC,
-        (true,  _)      => TimeType::Standard,
-        (false, false)  => TimeType::Wall,
+        // 2021 and 2022
+        let mut truncated = tz.clone();
+        truncated.truncate(1_609_459_200, 1_672_531_200).unwrap();
+        assert_eq!(truncated.footer, None);
+        assert_eq!(truncated.local_time_types[0].name, "-00");
+        assert_eq!(truncated.transitions.len(), 6);
+        assert_eq!(truncated.local_time_type_at(1_609_459_200).name, "CET");
+        assert_eq!(truncated.local_time_type_at(1_656_633_600).name, "CEST");
+        assert_eq!(truncated.local_time_type_at(1_672_531_200).name, "-00");
+        assert!(truncated.equivalent(&tz, 1_609_459_200 .. 1_672_531_200).unwrap());
+
+        let mut start_only = tz.clone();
+        start_only.truncate(1_609_459_200, i64::MAX).unwrap();
+        assert_eq!(start_only.transitions, vec![ Transition { timestamp: 1_609_459_200, local_time_type: 1 } ]);
+        assert!(start_only.equivalent(&tz, 1_609_459_200 .. 2_000_000_000).unwrap());
+
+        let mut end_only = tz.clone();
+        end_only.truncate(i64::MIN, 0).unwrap();
+        assert_eq!(end_only.local_time_types[0].name, "LMT");
+        assert_eq!(end_only.local_time_type_at(0).name, "-00");
+
+        let mut rule = TZData::from_posix_tz_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        rule.truncate(i64::MIN, 0).unwrap();
+        assert_eq!(rule.local_time_types[0].name, "CET");
+        assert_eq!(rule.transitions, vec![ Transition { timestamp: 0, local_time_type: 1 } ]);
+        assert_eq!(rule.local_time_type_at(0).name, "-00");
+
+        let mut rule = TZData::from_posix_tz_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert!(rule.truncate(0, i64::MAX - 1).is_err());
+
+        let mut empty = tz.clone();
+        assert!(empty.truncate(1_672_531_200, 1_609_459_200).is_err());
+        assert!(empty.truncate(0, 0).is_err());
+        assert_eq!(empty, tz);
+    }
+
+    #[test]
+    fn normalize() {
+        let mut tz = TZData::from_timespans(ltt(0, false, "GMT"), vec![
+            (1_616_893_200, ltt(3600, true, "BST")),
+            (1_625_097_600, LocalTimeType { indicators: Indicators::from(TimeType::Standard), .. ltt(3600, true, "BST") }),
+            (1_635_642_000, ltt(0, false, "GMT")),
+            (1_640_995_200, ltt(0, false, "GMT")),
+        ]);
+        tz.local_time_types.push(ltt(-3600, false, "XXX"));
+
+        let mut without_footer = tz.clone();
+        without_footer.normalize();
+        assert_eq!(without_footer.local_time_types, vec![ ltt(0, false, "GMT"), ltt(3600, true, "BST") ]);
+        assert_eq!(without_footer.transitions, vec![
+            Transition { timestamp: 1_616_893_200, local_time_type: 1 },
+            Transition { timestamp: 1_635_642_000, local_time_type: 0 },
+        ]);
+
+        tz.footer = Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned());
+        let mut with_footer = tz.clone();
+        with_footer.normalize();
+        assert_eq!(with_footer.transitions.len(), 3);
+        assert!(with_footer.equivalent(&tz, 1_577_836_800 .. 1_704_067_200).unwrap());
+    }
+
+    #[test]
+    fn posix_tz() {
+        let tz = TZData::from_posix_tz("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(tz.transitions.len(), 68 * 2);
+        assert_eq!(tz.transitions[0].timestamp, 7_520_400);
+        assert_eq!(tz.local_time_type_at(0).name, "CET");
+        assert_eq!(tz.local_time_type_at(1_720_000_000).name, "CEST");
+
+        let rule = TZData::from_posix_tz_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert!(rule.transitions.is_empty());
+        assert_eq!(rule.local_time_types.len(), 2);
+        assert!(tz.equivalent(&rule, 0 .. 2_500_000_000).unwrap());
+
+        // DST is in effect at the start of the year in the south.
+        let tz = TZData::from_posix_tz_years("AEST-10AEDT,M10.1.0,M4.1.0/3", 2024 ..= 2024).unwrap();
+        assert_eq!(tz.local_time_types[0].name, "AEDT");
+        assert_eq!(tz.transitions.len(), 2);
+
+        assert!(TZData::from_posix_tz("CET-1CEST,M3.5.0").is_err());
+    }
+
+    #[test]
+    fn fixed() {
+        let est = TZData::fixed(-18000, "EST").unwrap();
+        assert_eq!(est.local_time_types, vec![ ltt(-18000, false, "EST") ]);
+        assert!(est.transitions.is_empty());
+        assert_eq!(est.footer, Some("EST5".to_owned()));
+        assert!(est.is_fixed());
+
+        let india = TZData::fixed(19800, "+0530").unwrap();
+        assert_eq!(india.footer, Some("<+0530>-5:30".to_owned()));
+
+        let zulu = TZData::fixed(0, "Z").unwrap();
+        assert_eq!(zulu.footer, Some("<Z>0".to_owned()));
+        assert!(zulu.is_fixed());
+        assert_eq!(TZData::fixed(3600, "AB").unwrap().footer, Some("<AB>-1".to_owned()));
+
+        assert!(TZData::fixed(0, "").is_err());
+        assert!(TZData::fixed(200_000, "LMT").is_err());
+    }
+
+    #[test]
+    fn etc_gmt() {
+        let tz = TZData::from_etc_gmt("Etc/GMT+5").unwrap();
+        assert_eq!(tz.name, Some("Etc/GMT+5".to_owned()));
+        assert_eq!(tz.local_time_types, vec![ ltt(-18000, false, "-05") ]);
+        assert_eq!(tz.footer, Some("<-05>5".to_owned()));
+
+        let tz = TZData::from_etc_gmt("GMT-14").unwrap();
+        assert_eq!(tz.local_time_types, vec![ ltt(50400, false, "+14") ]);
+        assert_eq!(tz.footer, Some("<+14>-14".to_owned()));
+
+        let tz = TZData::from_etc_gmt("Etc/GMT+12").unwrap();
+        assert_eq!(tz.local_time_types, vec![ ltt(-43200, false, "-12") ]);
+
+        for name in &[ "Etc/GMT", "Etc/GMT0", "Etc/GMT+0", "Etc/GMT-0" ] {
+            let tz = TZData::from_etc_gmt(name).unwrap();
+            assert_eq!(tz.local_time_types, vec![ ltt(0, false, "GMT") ]);
+            assert_eq!(tz.footer, Some("GMT0".to_owned()));
+        }
+
+        for name in &[ "Etc/UTC", "Europe/London", "Etc/GMT+", "Etc/GMT+25", "Etc/GMT+5:30", "Etc/GMT++5",
+                      "Etc/GMT5", "GMT12", "Etc/GMT00", "Etc/GMT+13", "Etc/GMT-15", "Etc/GMT+24" ] {
+            assert_eq!(TZData::from_etc_gmt(name), None);
+        }
+    }
+
+    #[test]
+    fn changed_footer() {
+        let mut tz = TZData::from_posix_tz_rule("GMT0BST,M3.5.0/1,M10.5.0").unwrap();
+        let before = tz.clone();
+
+        // 2030-07-01
+        assert_eq!(tz.local_time_type_at(1_909_180_800).name, "BST");
+        assert_eq!(tz, before);
+
+        tz.footer = Some("GMT0".to_owned());
+        assert_eq!(tz.local_time_type_at(1_909_180_800).name, "GMT");
+        assert_eq!(tz.clone().local_time_type_at(1_909_180_800).name, "GMT");
+    }
+
+    #[test]
+    fn is_fixed() {
+        let mut tz = TZData::from_timespans(ltt(0, false, "GMT"), vec![ (0, ltt(0, false, "GMT")) ]);
+        assert!(tz.is_fixed());
+
+        tz.footer = Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned());
+        assert!(!tz.is_fixed());
+
+        let tz = TZData::from_timespans(ltt(-75, false, "LMT"), vec![ (-3_852_662_325, ltt(0, false, "GMT")) ]);
+        assert!(!tz.is_fixed());
     }
 }
@@ -11,6 +11,7 @@
 
 use byteorder::{ReadBytesExt, BigEndian};
 
+use std::convert::TryFrom;
 use std::error::Error as ErrorTrait;
 use std::fmt;
 use std::io::{Cursor, Read};
@@ -20,7 +21,8 @@ use std::result;
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Header {
 
-    /// The version of this file's format - either '\0', or '2', or '3'.
+    /// The version of this file's format - either `b'\0'`, or `b'2'`, or
+    /// `b'3'`, or `b'4'`.
     pub version: u8,
 
     /// The number of GMT flags in this file.
@@ -53,7 +55,7 @@ pub struct Header {
 pub struct TransitionData {
 
     /// The time at which the rules for computing local time change.
-    pub timestamp: i32,
+    pub timestamp: i64,
 
     /// Index into the local time types array for this transition.
     pub local_time_type_index: u8,
@@ -82,7 +84,7 @@ pub struct LocalTimeTypeData {
 pub struct LeapSecondData {
 
     /// The time, as a number of seconds, at which a leap second occurs.
-    pub timestamp: i32,
+    pub timestamp: i64,
 
     /// Number of leap seconds to be added.
     pub leap_second_count: i32,
@@ -99,9 +101,31 @@ pub struct LeapSecondData {
 /// file could try to read *gigabytes* of data while trying to read time zone
 /// information. To prevent this, reasonable defaults are set, although they
 /// can be turned off if necessary.
+///
+/// Files of version 2 or later contain two data blocks, each with its own
+/// header, so each one gets its own limits.
 #[derive(Debug, Copy, Clone)]
 pub struct Limits {
 
+    /// Limits for the first data block, which uses 32-bit times, and is
+    /// the only one in a version 1 file.
+    pub v1: BlockLimits,
+
+    /// Limits for the second data block of a version 2 or later file,
+    /// which uses 64-bit times.
+    pub v2: BlockLimits,
+
+    /// Maximum size of the whole file, in bytes
+    pub max_file_size: Option<u32>,
+
+    /// Maximum length of the POSIX TZ string in the footer, in bytes
+    pub max_footer_length: Option<u32>,
+}
+
+/// Maximum numbers of structures that can be loaded from one data block.
+#[derive(Debug, Copy, Clone)]
+pub struct BlockLimits {
+
     /// Maximum number of transition structures
     pub max_transitions: Option<u32>,
 
@@ -121,7 +145,83 @@ impl Limits {
     /// No size limits. This might use *lots* of memory when reading an
     /// invalid file, so be careful.
     pub fn none() -> Limits {
+        Limits::both(BlockLimits::none(), None, None)
+    }
+
+    /// A reasonable set of default values that pose no danger of using lots
+    /// of memory, while accepting every file compiled from the current time
+    /// zone database, including the `right/` zones and files compiled with
+    /// a far-off end year.
+    ///
+    /// The first data block only covers 1901 to 2038, so it gets the limits
+    /// from `tzfile.h`, and the second block gets more room.
+    pub fn sensible() -> Limits {
         Limits {
+            v1: BlockLimits::tzfile_h(),
+            v2: BlockLimits {
+                max_transitions: Some(10_000),
+                max_local_time_types: Some(256),
+                max_abbreviation_chars: Some(256),
+                max_leap_seconds: Some(100),
+            },
+            max_file_size: Some(1 << 20),
+            max_footer_length: Some(1024),
+        }
+    }
+
+    /// The limits that the reference implementation compiles in, from
+    /// `tzfile.h` at [ftp://ftp.iana.org/tz/code/tzfile.h], which apply to
+    /// both data blocks. Files that break these can’t be read by the C
+    /// library, even if they’re valid.
+    pub fn tzfile_h() -> Limits {
+        Limits::both(BlockLimits::tzfile_h(), None, None)
+    }
+
+    /// Tight limits, at roughly three times the largest values in the
+    /// current time zone database, for reading files from an untrusted
+    /// source.
+    pub fn strict() -> Limits {
+        let block = BlockLimits {
+            max_transitions: Some(1000),
+            max_local_time_types: Some(64),
+            max_abbreviation_chars: Some(128),
+            max_leap_seconds: Some(100),
+        };
+
+        Limits::both(block, Some(16 << 10), Some(128))
+    }
+
+    /// Loose limits, for files compiled with unusual options, that still
+    /// stop a file from claiming gigabytes of data.
+    pub fn generous() -> Limits {
+        let block = BlockLimits {
+            max_transitions: Some(1_000_000),
+            max_local_time_types: Some(256),
+            max_abbreviation_chars: Some(65_536),
+            max_leap_seconds: Some(10_000),
+        };
+
+        Limits::both(block, Some(64 << 20), Some(65_536))
+    }
+
+    fn both(block: BlockLimits, max_file_size: Option<u32>, max_footer_length: Option<u32>) -> Limits {
+        Limits { v1: block, v2: block, max_file_size, max_footer_length }
+    }
+
+    fn verify_file_size(self, size: usize) -> Result<()> {
+        check(Structures::FileBytes, u32::try_from(size).unwrap_or(u32::MAX), self.max_file_size)
+    }
+
+    fn verify_footer_length(self, length: usize) -> Result<()> {
+        check(Structures::FooterChars, u32::try_from(length).unwrap_or(u32::MAX), self.max_footer_length)
+    }
+}
+
+impl BlockLimits {
+
+    /// No size limits.
+    pub fn none() -> BlockLimits {
+        BlockLimits {
             max_transitions: None,
             max_local_time_types: None,
             max_abbreviation_chars: None,
@@ -129,13 +229,10 @@ impl Limits {
         }
     }
 
-    /// A reasonable set of default values that pose no danger of using lots
-    /// of memory.
-    ///
-    /// These values are taken from `tz_file.h`, at
-    /// [ftp://ftp.iana.org/tz/code/tzfile.h].
-    pub fn sensible() -> Limits {
-        Limits {
+    /// The values of `TZ_MAX_TIMES`, `TZ_MAX_TYPES`, `TZ_MAX_CHARS`, and
+    /// `TZ_MAX_LEAPS` from `tzfile.h`.
+    pub fn tzfile_h() -> BlockLimits {
+        BlockLimits {
             max_transitions: Some(2000),
             max_local_time_types: Some(256),
             max_abbreviation_chars: Some(50),
@@ -147,19 +244,6 @@ impl Limits {
     /// of limits. Returns `Ok(())` if everything is within the limits, and a
     /// boxed `Error` if at least one count is over.
     pub fn verify(self, header: &Header) -> Result<()> {
-        let check = |structures, intended_count, limit| {
-            if let Some(max) = limit {
-                if intended_count > max {
-                    return Err(Error::LimitReached {
-                        structures,
-                        intended_count,
-                        limit: max,
-                    });
-                }
-            }
-            Ok(())
-        };
-
         check(Structures::Transitions,       header.num_transitions,      self.max_transitions)?;
         check(Structures::LocalTimeTypes,    header.num_local_time_types, self.max_local_time_types)?;
         check(Structures::LeapSeconds,       header.num_leap_seconds,     self.max_leap_seconds)?;
@@ -171,6 +255,15 @@ impl Limits {
     }
 }
 
+fn check(structures: Structures, intended_count: u32, limit: Option<u32>) -> Result<()> {
+    match limit {
+        Some(max) if intended_count > max => {
+            Err(Box::new(Error::LimitReached { structures, intended_count, limit: max }))
+        },
+        _ => Ok(()),
+    }
+}
+
 
 struct Parser {
     cursor: Cursor<Vec<u8>>,
@@ -185,7 +278,7 @@ impl Parser {
 
     fn read_magic_number(&mut self) -> Result<()> {
         let mut magic = [0u8; 4];
-        self.cursor.read(&mut magic)?;
+        self.cursor.read_exact(&mut magic)?;
         if magic == *b"TZif" {
             Ok(())
         }
@@ -194,15 +287,15 @@ impl Parser {
         }
     }
 
-    fn skip_initial_zeroes(&mut self) -> Result<()> {
-        let mut magic = [0u8; 15];
-        self.cursor.read(&mut magic)?;
-        Ok(())
-    }
-
     fn read_header(&mut self) -> Result<Header> {
+        let version = self.cursor.read_u8()?;
+
+        // The version is followed by fifteen bytes reserved for future use.
+        let mut reserved = [0u8; 15];
+        self.cursor.read_exact(&mut reserved)?;
+
         Ok(Header {
-            version:               self.cursor.read_u8()?,
+            version,
             num_gmt_flags:         self.cursor.read_u32::<BigEndian>()?,
             num_standard_flags:    self.cursor.read_u32::<BigEndian>()?,
             num_leap_seconds:      self.cursor.read_u32::<BigEndian>()?,
@@ -212,10 +305,41 @@ impl Parser {
         })
     }
 
-    fn read_transition_data(&mut self, count: usize) -> Result<Vec<TransitionData>> {
+    /// Skips over the version 1 data block described by the given header,
+    /// which later versions of the format follow with a copy that uses
+    /// 64-bit times.
+    fn skip_data_block(&mut self, header: &Header) -> Result<()> {
+        let length = u64::from(header.num_transitions) * 5
+                   + u64::from(header.num_local_time_types) * 6
+                   + u64::from(header.num_abbr_chars)
+                   + u64::from(header.num_leap_seconds) * 8
+                   + u64::from(header.num_standard_flags)
+                   + u64::from(header.num_gmt_flags);
+
+        let position = self.cursor.position() + length;
+        if position > self.cursor.get_ref().len() as u64 {
+            return Err(Box::new(Error::Truncated));
+        }
+
+        self.cursor.set_position(position);
+        Ok(())
+    }
+
+    /// Reads a timestamp, which is 64 bits wide in the second data block of
+    /// later versions of the format, and 32 bits wide otherwise.
+    fn read_timestamp(&mut self, wide: bool) -> Result<i64> {
+        if wide {
+            Ok(self.cursor.read_i64::<BigEndian>()?)
+        }
+        else {
+            Ok(i64::from(self.cursor.read_i32::<BigEndian>()?))
+        }
+    }
+
+    fn read_transition_data(&mut self, count: usize, wide: bool) -> Result<Vec<TransitionData>> {
         let mut times = Vec::with_capacity(count);
         for _ in 0 .. count {
-            times.push(self.cursor.read_i32::<BigEndian>()?);
+            times.push(self.read_timestamp(wide)?);
         }
 
         let mut types = Vec::with_capacity(count);
@@ -251,16 +375,43 @@ impl Parser {
         Ok(buf)
     }
 
-    fn read_leap_second_data(&mut self, count: usize) -> Result<Vec<LeapSecondData>> {
+    fn read_leap_second_data(&mut self, count: usize, wide: bool) -> Result<Vec<LeapSecondData>> {
         let mut buf = Vec::with_capacity(count);
         for _ in 0 .. count {
             buf.push(LeapSecondData {
-                timestamp:          self.cursor.read_i32::<BigEndian>()?,
+                timestamp:          self.read_timestamp(wide)?,
                 leap_second_count:  self.cursor.read_i32::<BigEndian>()?,
             });
         }
         Ok(buf)
     }
+
+    /// Reads the footer that follows the second data block: a POSIX TZ
+    /// string on its own line, which may be empty.
+    fn read_footer(&mut self, limits: Limits) -> Result<Option<String>> {
+        if self.cursor.read_u8()? != b'\n' {
+            return Err(Box::new(Error::InvalidFooter));
+        }
+
+        let rest = &self.cursor.get_ref()[self.cursor.position() as usize ..];
+        limits.verify_footer_length(rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len()))?;
+
+        let mut footer = Vec::new();
+        loop {
+            match self.cursor.read_u8() {
+                Ok(b'\n')  => break,
+                Ok(byte)   => footer.push(byte),
+                Err(_)     => return Err(Box::new(Error::InvalidFooter)),
+            }
+        }
+
+        if footer.is_empty() {
+            Ok(None)
+        }
+        else {
+            Ok(Some(String::from_utf8(footer)?))
+        }
+    }
 }
 
 
@@ -295,14 +446,50 @@ pub enum Error {
     /// should always contain at least one, so we know what the *base* offset
     /// from UTC is.)
     NoTransitions,
+
+    /// The error when the buffer ends in the middle of a data block.
+    Truncated,
+
+    /// The error when the footer of a version 2 or later file isn’t a line
+    /// of text.
+    InvalidFooter,
+
+    /// The error when a file doesn’t contain any local time types, so
+    /// there’s no way to know the local time at all.
+    NoLocalTimeTypes,
+
+    /// The error when a transition refers to a local time type that doesn’t
+    /// exist.
+    InvalidLocalTimeTypeIndex {
+
+        /// The index that the transition refers to.
+        index: u8,
+    },
+
+    /// The error when transitions aren’t in ascending order of time.
+    UnsortedTransitions,
+
+    /// The error when an abbreviation would start further into the
+    /// characters than a local time type can refer to.
+    AbbreviationOutOfRange {
+
+        /// The position where the abbreviation would start.
+        name_offset: usize,
+    },
 }
 
 impl ErrorTrait for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::InvalidMagicNumber   => "invalid magic number",
-            Error::LimitReached { .. }  => "limit reached",
-            Error::NoTransitions        => "no transitions",
+            Error::InvalidMagicNumber                => "invalid magic number",
+            Error::LimitReached { .. }               => "limit reached",
+            Error::NoTransitions                     => "no transitions",
+            Error::Truncated                         => "truncated data",
+            Error::InvalidFooter                     => "invalid footer",
+            Error::NoLocalTimeTypes                  => "no local time types",
+            Error::InvalidLocalTimeTypeIndex { .. }  => "invalid local time type index",
+            Error::UnsortedTransitions               => "unsorted transitions",
+            Error::AbbreviationOutOfRange { .. }     => "abbreviation out of range",
         }
     }
 }
@@ -319,6 +506,22 @@ impl fmt::Display for Error {
             Error::NoTransitions => {
                 write!(f, "read 0 transitions")
             },
+
+            Error::Truncated => write!(f, "data ended early"),
+
+            Error::InvalidFooter => write!(f, "invalid footer"),
+
+            Error::NoLocalTimeTypes => write!(f, "read 0 local time types"),
+
+            Error::InvalidLocalTimeTypeIndex { index } => {
+                write!(f, "transition refers to local time type {}, which doesn’t exist", index)
+            },
+
+            Error::UnsortedTransitions => write!(f, "transitions are out of order"),
+
+            Error::AbbreviationOutOfRange { name_offset } => {
+                write!(f, "abbreviation would start at character {}, past the last one a local time type can refer to", name_offset)
+            },
         }
     }
 }
@@ -334,6 +537,8 @@ pub enum Structures {
     GMTFlags,
     StandardFlags,
     TimezoneAbbrChars,
+    FileBytes,
+    FooterChars,
 }
 
 impl fmt::Display for Structures {
@@ -345,6 +550,8 @@ impl fmt::Display for Structures {
             Structures::GMTFlags           => "GMT flags".fmt(f),
             Structures::StandardFlags      => "Standard Time flags".fmt(f),
             Structures::TimezoneAbbrChars  => "timezone abbreviation chars".fmt(f),
+            Structures::FileBytes          => "bytes in the file".fmt(f),
+            Structures::FooterChars        => "footer chars".fmt(f),
         }
     }
 }
@@ -360,24 +567,160 @@ pub struct TZData {
     pub strings: Vec<u8>,
     pub standard_flags: Vec<u8>,
     pub gmt_flags: Vec<u8>,
+
+    /// The POSIX TZ string describing the rules in effect after the last
+    /// transition, such as `CET-1CEST,M3.5.0,M10.5.0/3`. Only files of
+    /// version 2 or later have one, and it may be absent even then.
+    pub footer: Option<String>,
+}
+
+impl TZData {
+
+    /// Shrinks this structure without changing the local times it
+    /// describes, for keeping many zones in memory.
+    ///
+    /// Transitions that don’t change the offset, abbreviation, or DST flag
+    /// are removed, except for the last one if there’s a footer, as it marks
+    /// where the footer takes over. Identical local time types are merged,
+    /// unused ones are removed, and each abbreviation is stored once, with
+    /// the indices and the header’s counts updated to match. Local time type
+    /// 0 stays first, as it’s in effect before the first transition.
+    ///
+    /// Returns an error, leaving the data alone, if there are no local time
+    /// types, if a transition refers to a local time type that doesn’t
+    /// exist, or if the abbreviations would end up too far into the
+    /// characters for a local time type to refer to.
+    pub fn compact(&mut self) -> Result<()> {
+        if self.time_info.is_empty() {
+            return Err(Box::new(Error::NoLocalTimeTypes));
+        }
+
+        if let Some(t) = self.transitions.iter().find(|t| usize::from(t.local_time_type_index) >= self.time_info.len()) {
+            return Err(Box::new(Error::InvalidLocalTimeTypeIndex { index: t.local_time_type_index }));
+        }
+
+        let abbreviation = |info: &LocalTimeTypeData| -> Vec<u8> {
+            self.strings.iter()
+                        .skip(usize::from(info.name_offset))
+                        .take_while(|&&c| c != 0)
+                        .cloned()
+                        .collect()
+        };
+
+        let local_time = |index: u8| {
+            let info = &self.time_info[usize::from(index)];
+            (info.offset, info.is_dst != 0, abbreviation(info))
+        };
+
+        let local_time_type = |index: u8| {
+            let i = usize::from(index);
+            (local_time(index), self.standard_flags.get(i).cloned(), self.gmt_flags.get(i).cloned())
+        };
+
+        // Only keep transitions that change the local time, and give each
+        // distinct type that’s still used a new index.
+        let mut types = vec![ local_time_type(0) ];
+        let mut current = 0;
+        let mut transitions = Vec::with_capacity(self.transitions.len());
+        let footer_start = if self.footer.is_some() { self.transitions.len().checked_sub(1) } else { None };
+
+        for (i, t) in self.transitions.iter().enumerate() {
+            if local_time(t.local_time_type_index) == local_time(current) && footer_start != Some(i) {
+                continue;
+            }
+
+            current = t.local_time_type_index;
+            let ltt = local_time_type(current);
+            let index = match types.iter().position(|t| *t == ltt) {
+                Some(index) => index,
+                None => {
+                    types.push(ltt);
+                    types.len() - 1
+                },
+            };
+
+            transitions.push(TransitionData { timestamp: t.timestamp, local_time_type_index: index as u8 });
+        }
+
+        let mut time_info = Vec::with_capacity(types.len());
+        let mut strings: Vec<u8> = Vec::new();
+        let has_standard_flags = !self.standard_flags.is_empty();
+        let has_gmt_flags = !self.gmt_flags.is_empty();
+        let mut standard_flags = Vec::new();
+        let mut gmt_flags = Vec::new();
+
+        for ((offset, is_dst, name), standard_flag, gmt_flag) in types {
+            let mut terminated = name;
+            terminated.push(0);
+
+            // Abbreviations can share the end of a longer one, such as
+            // “JST” at the end of “CJST”.
+            let name_offset = match strings.windows(terminated.len()).position(|w| w == &terminated[..]) {
+                Some(position) => position,
+                None => {
+                    strings.extend_from_slice(&terminated);
+                    strings.len() - terminated.len()
+                },
+            };
+
+            let name_offset = u8::try_from(name_offset)
+                                  .map_err(|_| Error::AbbreviationOutOfRange { name_offset })?;
+
+            time_info.push(LocalTimeTypeData { offset, is_dst: is_dst as u8, name_offset });
+
+            if has_standard_flags {
+                standard_flags.push(standard_flag.unwrap_or(0));
+            }
+
+            if has_gmt_flags {
+                gmt_flags.push(gmt_flag.unwrap_or(0));
+            }
+        }
+
+        self.header.num_transitions = transitions.len() as u32;
+        self.header.num_local_time_types = time_info.len() as u32;
+        self.header.num_abbr_chars = strings.len() as u32;
+        self.header.num_standard_flags = standard_flags.len() as u32;
+        self.header.num_gmt_flags = gmt_flags.len() as u32;
+
+        self.transitions = transitions;
+        self.time_info = time_info;
+        self.strings = strings;
+        self.standard_flags = standard_flags;
+        self.gmt_flags = gmt_flags;
+        Ok(())
+    }
 }
 
 /// Parse a series of bytes into a `TZData` structure, returning an error if
 /// the buffer fails to be read from, or a limit is reached.
+///
+/// Files of version 2 or later contain their data twice, first with 32-bit
+/// times and then with 64-bit ones; only the second copy is returned.
 pub fn parse(buf: Vec<u8>, limits: Limits) -> Result<TZData> {
+    limits.verify_file_size(buf.len())?;
+
     let mut parser = Parser::new(buf);
     parser.read_magic_number()?;
-    parser.skip_initial_zeroes()?;
 
-    let header = parser.read_header()?;
-    limits.verify(&header)?;
+    let mut header = parser.read_header()?;
+    limits.v1.verify(&header)?;
 
-    let transitions    = parser.read_transition_data(header.num_transitions as usize)?;
+    let wide = header.version >= b'2';
+    if wide {
+        parser.skip_data_block(&header)?;
+        parser.read_magic_number()?;
+        header = parser.read_header()?;
+        limits.v2.verify(&header)?;
+    }
+
+    let transitions    = parser.read_transition_data(header.num_transitions as usize, wide)?;
     let time_info      = parser.read_local_time_type_data(header.num_local_time_types as usize)?;
     let strings        = parser.read_octets(header.num_abbr_chars as usize)?;
-    let leap_seconds   = parser.read_leap_second_data(header.num_leap_seconds as usize)?;
+    let leap_seconds   = parser.read_leap_second_data(header.num_leap_seconds as usize, wide)?;
     let standard_flags = parser.read_octets(header.num_standard_flags as usize)?;
     let gmt_flags      = parser.read_octets(header.num_gmt_flags as usize)?;
+    let footer         = if wide { parser.read_footer(limits)? } else { None };
 
     Ok(TZData {
         header,
@@ -387,6 +730,7 @@ pub fn parse(buf: Vec<u8>, limits: Limits) -> Result<TZData> {
         strings,
         standard_flags,
         gmt_flags,
+        footer,
     })
 }
 
@@ -457,4 +801,138 @@ mod test {
             LocalTimeTypeData { offset: 32400, is_dst: 0, name_offset: 9 },
         ]);
     }
+
+    #[test]
+    fn compact() {
+        let mut data = TZData {
+            header: Header {
+                version: b'2', num_gmt_flags: 0, num_standard_flags: 0, num_leap_seconds: 0,
+                num_transitions: 4, num_local_time_types: 4, num_abbr_chars: 13,
+            },
+            transitions: vec![
+                TransitionData { timestamp: -1_017_824_400, local_time_type_index: 2 },
+                TransitionData { timestamp:   -683_794_800, local_time_type_index: 1 },
+                TransitionData { timestamp:   -680_000_000, local_time_type_index: 3 },
+                TransitionData { timestamp:   -672_393_600, local_time_type_index: 2 },
+            ],
+            time_info: vec![
+                LocalTimeTypeData { offset: 32400, is_dst: 0, name_offset: 0 },
+                LocalTimeTypeData { offset: 36000, is_dst: 1, name_offset: 5 },
+                LocalTimeTypeData { offset: 32400, is_dst: 0, name_offset: 9 },
+                LocalTimeTypeData { offset: 36000, is_dst: 1, name_offset: 5 },
+            ],
+            leap_seconds: Vec::new(),
+            strings: b"CJST\0JDT\0JST\0".to_vec(),
+            standard_flags: Vec::new(),
+            gmt_flags: Vec::new(),
+            footer: None,
+        };
+
+        data.compact().unwrap();
+        assert_eq!(data.header.num_transitions, 3);
+        assert_eq!(data.header.num_local_time_types, 3);
+        assert_eq!(data.strings, b"CJST\0JDT\0".to_vec());
+        assert_eq!(data.time_info[1], LocalTimeTypeData { offset: 32400, is_dst: 0, name_offset: 1 });
+        assert_eq!(data.transitions[2], TransitionData { timestamp: -672_393_600, local_time_type_index: 1 });
+    }
+
+    #[test]
+    fn compact_out_of_range() {
+        // Type 0’s abbreviation is the end of type 1’s, so once type 0’s
+        // comes first, there’s no room for type 1’s to start in range.
+        let mut strings = vec![ b'X'; 600 ];
+        strings.push(0);
+
+        let mut data = TZData {
+            header: Header {
+                version: b'2', num_gmt_flags: 0, num_standard_flags: 0, num_leap_seconds: 0,
+                num_transitions: 1, num_local_time_types: 2, num_abbr_chars: 601,
+            },
+            transitions: vec![ TransitionData { timestamp: 0, local_time_type_index: 1 } ],
+            time_info: vec![
+                LocalTimeTypeData { offset:    0, is_dst: 0, name_offset: 255 },
+                LocalTimeTypeData { offset: 3600, is_dst: 0, name_offset: 0 },
+            ],
+            leap_seconds: Vec::new(),
+            strings,
+            standard_flags: Vec::new(),
+            gmt_flags: Vec::new(),
+            footer: None,
+        };
+
+        let original = data.clone();
+        assert!(data.compact().is_err());
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn compact_invalid_index() {
+        let mut data = TZData {
+            header: Header {
+                version: b'2', num_gmt_flags: 0, num_standard_flags: 0, num_leap_seconds: 0,
+                num_transitions: 1, num_local_time_types: 1, num_abbr_chars: 4,
+            },
+            transitions: vec![ TransitionData { timestamp: 0, local_time_type_index: 1 } ],
+            time_info: vec![ LocalTimeTypeData { offset: 0, is_dst: 0, name_offset: 0 } ],
+            leap_seconds: Vec::new(),
+            strings: b"GMT\0".to_vec(),
+            standard_flags: Vec::new(),
+            gmt_flags: Vec::new(),
+            footer: None,
+        };
+
+        let original = data.clone();
+        let error = data.compact().unwrap_err();
+        assert_eq!(error.to_string(), "transition refers to local time type 1, which doesn’t exist");
+        assert_eq!(data, original);
+
+        data.time_info.clear();
+        assert_eq!(data.compact().unwrap_err().to_string(), "read 0 local time types");
+    }
+
+    #[test]
+    fn limits() {
+        let data = TZData {
+            header: Header {
+                version: b'2', num_gmt_flags: 0, num_standard_flags: 0, num_leap_seconds: 0,
+                num_transitions: 3, num_local_time_types: 2, num_abbr_chars: 8,
+            },
+            transitions: vec![
+                TransitionData { timestamp: -4_000_000_000, local_time_type_index: 1 },
+                TransitionData { timestamp:              0, local_time_type_index: 0 },
+                TransitionData { timestamp:  4_000_000_000, local_time_type_index: 1 },
+            ],
+            time_info: vec![
+                LocalTimeTypeData { offset:    0, is_dst: 0, name_offset: 0 },
+                LocalTimeTypeData { offset: 3600, is_dst: 1, name_offset: 4 },
+            ],
+            leap_seconds: Vec::new(),
+            strings: b"GMT\0BST\0".to_vec(),
+            standard_flags: Vec::new(),
+            gmt_flags: Vec::new(),
+            footer: Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned()),
+        };
+        let bytes = super::super::writer::write(&data);
+
+        for limits in &[ Limits::none(), Limits::sensible(), Limits::tzfile_h(), Limits::strict(), Limits::generous() ] {
+            assert_eq!(parse(bytes.clone(), *limits).unwrap(), data);
+        }
+
+        // Only one of the transitions fits in 32 bits.
+        let mut limits = Limits::none();
+        limits.v1.max_transitions = Some(1);
+        assert!(parse(bytes.clone(), limits).is_ok());
+        limits.v2.max_transitions = Some(1);
+        assert!(parse(bytes.clone(), limits).is_err());
+
+        let mut limits = Limits::none();
+        limits.max_footer_length = Some(23);
+        assert!(parse(bytes.clone(), limits).is_err());
+        limits.max_footer_length = Some(24);
+        assert!(parse(bytes.clone(), limits).is_ok());
+
+        let mut limits = Limits::none();
+        limits.max_file_size = Some(bytes.len() as u32 - 1);
+        assert!(parse(bytes, limits).is_err());
+    }
 }
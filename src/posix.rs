@@ -0,0 +1,594 @@
+//! Parsing POSIX TZ strings
+//!
+//! Version 2 and later zoneinfo files end with a footer in the format of
+//! the `TZ` environment variable, such as `CET-1CEST,M3.5.0,M10.5.0/3`,
+//! which gives the rule for local time after the file’s last transition.
+//! This module parses those strings, including the extensions to POSIX
+//! from RFC 8536, and works out the transitions that their rules produce.
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::ops::Range;
+use std::result;
+
+use super::{Indicators, LocalTimeType, Result};
+use zi::{days_from_civil, days_in_month, DaySpec};
+
+
+/// The rule that daylight-saving time follows when a string gives a DST
+/// abbreviation without any dates, which is the US rule, as with tzcode.
+const DEFAULT_RULE: &str = "M3.2.0,M11.1.0";
+
+/// The time of day that daylight-saving time starts and ends when a rule
+/// doesn’t say.
+const DEFAULT_TIME: i64 = 2 * 60 * 60;
+
+/// The largest number of hours a zone’s offset can have.
+const MAX_OFFSET_HOURS: i64 = 24;
+
+/// The largest number of hours a rule’s time of day can have, as RFC 8536
+/// allows times from -167 to 167 hours.
+const MAX_TIME_HOURS: i64 = 167;
+
+/// The number of seconds in an average Gregorian year, used to work out
+/// roughly which year a timestamp falls in.
+const SECONDS_PER_YEAR: i64 = 31_556_952;
+
+/// The most years that `checked_transitions` works out a rule’s transitions
+/// over at once. This is far more than any real use needs, and keeps the
+/// work down to twenty thousand transitions.
+pub const MAX_YEARS: i64 = 10_000;
+
+
+/// A parsed POSIX TZ string.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PosixTz {
+
+    /// The local time type for standard time.
+    pub std: LocalTimeType,
+
+    /// The local time type for daylight-saving time, and the rule for when
+    /// it’s in effect, if the zone has any.
+    pub dst: Option<DstRule>,
+}
+
+/// When a zone observes daylight-saving time each year.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DstRule {
+
+    /// The local time type for daylight-saving time.
+    pub local_time_type: LocalTimeType,
+
+    /// The day that daylight-saving time starts.
+    pub start: RuleDate,
+
+    /// The time of day, in seconds of local standard time, that
+    /// daylight-saving time starts. This can be negative, or over a day.
+    pub start_time: i64,
+
+    /// The day that daylight-saving time ends.
+    pub end: RuleDate,
+
+    /// The time of day, in seconds of local daylight-saving time, that
+    /// daylight-saving time ends. This can be negative, or over a day.
+    pub end_time: i64,
+}
+
+/// A day of the year in a POSIX TZ rule.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RuleDate {
+
+    /// A day of the year from 1 to 365 that never counts 29 February, such
+    /// as `J60` for 1 March.
+    Julian(u16),
+
+    /// A day of the year from 0 to 365 that counts 29 February in leap
+    /// years, such as `59`.
+    ZeroBased(u16),
+
+    /// A weekday in a week of a month, such as `M3.5.0` for the last Sunday
+    /// in March. Weeks go from 1 to 5, where 5 means the last one.
+    MonthWeekDay {
+
+        /// The month, from 1 to 12.
+        month: u8,
+
+        /// The week of the month, from 1 to 5.
+        week: u8,
+
+        /// The day of the week, as a number of days since Sunday.
+        weekday: u8,
+    },
+}
+
+
+/// Parses a POSIX TZ string, such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+pub fn parse(input: &str) -> Result<PosixTz> {
+    let mut parser = Parser { input: input.as_bytes(), position: 0 };
+
+    let std_name = parser.read_abbreviation()?;
+    let std_offset = parser.read_offset()?;
+    let std = local_time_type(std_name, std_offset, false);
+
+    let dst = if parser.at_end() {
+        None
+    }
+    else {
+        let dst_name = parser.read_abbreviation()?;
+        let dst_offset = match parser.peek() {
+            Some(b',') | None  => std_offset + 60 * 60,
+            _                  => parser.read_offset()?,
+        };
+
+        if parser.at_end() {
+            parser = Parser { input: DEFAULT_RULE.as_bytes(), position: 0 };
+        }
+        else {
+            parser.expect(b',')?;
+        }
+
+        let (start, start_time) = parser.read_rule_date()?;
+        parser.expect(b',')?;
+        let (end, end_time) = parser.read_rule_date()?;
+
+        Some(DstRule {
+            local_time_type: local_time_type(dst_name, dst_offset, true),
+            start, start_time, end, end_time,
+        })
+    };
+
+    if !parser.at_end() {
+        return Err(Box::new(Error::TrailingCharacters));
+    }
+
+    Ok(PosixTz { std, dst })
+}
+
+/// Formats a local time type that never changes as a TZ string, in the
+/// same way as zic, such as `EST5` or `<+0530>-5:30`.
+pub(crate) fn format_fixed(ltt: &LocalTimeType) -> String {
+    format_abbreviation(&ltt.name) + &format_time(-ltt.offset)
+}
+
+/// Formats an abbreviation for a TZ string. Abbreviations that aren’t at
+/// least three letters get quoted, so they can be read back.
+pub(crate) fn format_abbreviation(name: &str) -> String {
+    if name.len() >= 3 && name.bytes().all(|c| c.is_ascii_alphabetic()) {
+        name.to_owned()
+    }
+    else {
+        format!("<{}>", name)
+    }
+}
+
+/// Formats a number of seconds as a time for a TZ string, such as `-5` or
+/// `5:30`, leaving out the minutes and seconds when they’re zero. Offsets
+/// need their sign flipping first, as POSIX counts them west of UT.
+pub(crate) fn format_time(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let total = seconds.unsigned_abs();
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+
+    match (minutes, seconds) {
+        (0, 0) => format!("{}{}", sign, hours),
+        (m, 0) => format!("{}{}:{:02}", sign, hours, m),
+        (m, s) => format!("{}{}:{:02}:{:02}", sign, hours, m, s),
+    }
+}
+
+fn local_time_type(name: String, offset: i64, is_dst: bool) -> LocalTimeType {
+    LocalTimeType { name, offset, is_dst, indicators: Indicators::UNSPECIFIED }
+}
+
+
+/// A cursor over the bytes of a TZ string.
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).cloned()
+    }
+
+    fn at_end(&self) -> bool {
+        self.position == self.input.len()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        }
+        else {
+            Err(Box::new(Error::InvalidRule))
+        }
+    }
+
+    /// Reads the characters that match a predicate, returning them.
+    fn take_while<F: Fn(u8) -> bool>(&mut self, predicate: F) -> &'a [u8] {
+        let start = self.position;
+        while self.peek().map_or(false, &predicate) {
+            self.position += 1;
+        }
+
+        &self.input[start .. self.position]
+    }
+
+    /// Reads an abbreviation, which is either at least three letters, or
+    /// letters, digits, and signs quoted in angle brackets. Quoted ones can
+    /// be shorter than three characters, as tzcode allows, so that zones
+    /// with abbreviations such as “Z” can still have a footer.
+    fn read_abbreviation(&mut self) -> Result<String> {
+        let (name, min_length) = if self.peek() == Some(b'<') {
+            self.position += 1;
+            let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == b'+' || c == b'-');
+            if self.peek() != Some(b'>') {
+                return Err(Box::new(Error::InvalidAbbreviation));
+            }
+
+            self.position += 1;
+            (name, 1)
+        }
+        else {
+            (self.take_while(|c| c.is_ascii_alphabetic()), 3)
+        };
+
+        if name.len() < min_length {
+            return Err(Box::new(Error::InvalidAbbreviation));
+        }
+
+        Ok(String::from_utf8_lossy(name).into_owned())
+    }
+
+    /// Reads a zone’s offset, which POSIX gives as the number of seconds to
+    /// add to local time to get UT, so its sign is the other way around.
+    fn read_offset(&mut self) -> Result<i64> {
+        match self.read_time(MAX_OFFSET_HOURS) {
+            Some(offset) => Ok(-offset),
+            None         => Err(Box::new(Error::InvalidOffset)),
+        }
+    }
+
+    /// Reads a time in the form `[+-]hh[:mm[:ss]]`, as a number of seconds.
+    fn read_time(&mut self, max_hours: i64) -> Option<i64> {
+        let sign = match self.peek() {
+            Some(b'-')  => { self.position += 1; -1 },
+            Some(b'+')  => { self.position += 1;  1 },
+            _           => 1,
+        };
+
+        let hours = self.read_number()?;
+        if hours > max_hours {
+            return None;
+        }
+
+        let mut seconds = hours * 60 * 60;
+        for &multiplier in &[ 60, 1 ] {
+            if self.peek() != Some(b':') {
+                break;
+            }
+
+            self.position += 1;
+            match self.read_number()? {
+                n if n < 60  => seconds += n * multiplier,
+                _            => return None,
+            }
+        }
+
+        Some(sign * seconds)
+    }
+
+    fn read_number(&mut self) -> Option<i64> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() || digits.len() > 3 {
+            return None;
+        }
+
+        Some(digits.iter().fold(0, |n, &d| n * 10 + i64::from(d - b'0')))
+    }
+
+    /// Reads the date and optional time of one end of a rule.
+    fn read_rule_date(&mut self) -> Result<(RuleDate, i64)> {
+        let date = match self.peek() {
+            Some(b'J') => {
+                self.position += 1;
+                match self.read_number() {
+                    Some(day @ 1 ..= 365)  => RuleDate::Julian(day as u16),
+                    _                      => return Err(Box::new(Error::InvalidRule)),
+                }
+            },
+
+            Some(b'M') => {
+                self.position += 1;
+                let month = self.read_number();
+                self.expect(b'.')?;
+                let week = self.read_number();
+                self.expect(b'.')?;
+                let weekday = self.read_number();
+
+                match (month, week, weekday) {
+                    (Some(month @ 1 ..= 12), Some(week @ 1 ..= 5), Some(weekday @ 0 ..= 6)) => {
+                        RuleDate::MonthWeekDay { month: month as u8, week: week as u8, weekday: weekday as u8 }
+                    },
+                    _ => return Err(Box::new(Error::InvalidRule)),
+                }
+            },
+
+            _ => {
+                match self.read_number() {
+                    Some(day @ 0 ..= 365)  => RuleDate::ZeroBased(day as u16),
+                    _                      => return Err(Box::new(Error::InvalidRule)),
+                }
+            },
+        };
+
+        let time = if self.peek() == Some(b'/') {
+            self.position += 1;
+            self.read_time(MAX_TIME_HOURS).ok_or(Error::InvalidRule)?
+        }
+        else {
+            DEFAULT_TIME
+        };
+
+        Ok((date, time))
+    }
+}
+
+
+impl PosixTz {
+
+    /// Returns the local time type in effect at the given Unix timestamp.
+    pub fn local_time_type_at(&self, timestamp: i64) -> &LocalTimeType {
+        let rule = match self.dst {
+            Some(ref rule)  => rule,
+            None            => return &self.std,
+        };
+
+        let year = estimate_year(timestamp);
+        let changes = rule.changes(self.std.offset, year - 2, year + 1);
+        match changes.iter().rev().find(|c| c.0 <= timestamp).or_else(|| changes.first()) {
+            Some(&(_, true))   => &rule.local_time_type,
+            _                  => &self.std,
+        }
+    }
+
+    /// Returns the local time type for daylight-saving time if `is_dst` is
+    /// true and there is one, or the one for standard time otherwise.
+    pub(crate) fn local_time_type(&self, is_dst: bool) -> &LocalTimeType {
+        match self.dst {
+            Some(ref rule) if is_dst  => &rule.local_time_type,
+            _                         => &self.std,
+        }
+    }
+
+    /// Returns each transition in the given range of Unix timestamps, along
+    /// with the local time type it changes to.
+    ///
+    /// As a rule produces two transitions a year, the range should only
+    /// cover as many years as are needed; `checked_transitions` refuses
+    /// ranges that are too long.
+    pub fn transitions(&self, range: Range<i64>) -> Vec<(i64, &LocalTimeType)> {
+        let rule = match self.dst {
+            Some(ref rule)  => rule,
+            None            => return Vec::new(),
+        };
+
+        if range.start >= range.end {
+            return Vec::new();
+        }
+
+        // Start a couple of years early, so the first transition in the
+        // range only counts if it actually changes anything.
+        let changes = rule.changes(self.std.offset, estimate_year(range.start) - 2, estimate_year(range.end - 1) + 1);
+        changes.into_iter()
+               .filter(|c| range.start <= c.0 && c.0 < range.end)
+               .map(|(timestamp, is_dst)| (timestamp, if is_dst { &rule.local_time_type } else { &self.std }))
+               .collect()
+    }
+
+    /// Returns each transition in the given range of Unix timestamps, in the
+    /// same way as `transitions`, or an error if the zone has a rule and the
+    /// range covers more than `MAX_YEARS` years.
+    pub fn checked_transitions(&self, range: Range<i64>) -> Result<Vec<(i64, &LocalTimeType)>> {
+        if self.dst.is_some() && range.start < range.end && estimate_year(range.end - 1) - estimate_year(range.start) > MAX_YEARS {
+            return Err(Box::new(Error::RangeTooLarge));
+        }
+
+        Ok(self.transitions(range))
+    }
+}
+
+impl DstRule {
+
+    /// Returns the times that daylight-saving time starts and ends in each
+    /// year in the range, as timestamps along with whether DST is in effect
+    /// from then on, leaving out any that don’t change anything. Start
+    /// times are given in standard time, so this needs its offset.
+    fn changes(&self, std_offset: i64, first_year: i64, last_year: i64) -> Vec<(i64, bool)> {
+        let mut changes = Vec::new();
+
+        for year in first_year ..= last_year {
+            let start = self.start.to_days(year).saturating_mul(86400).saturating_add(self.start_time - std_offset);
+            let end = self.end.to_days(year).saturating_mul(86400).saturating_add(self.end_time - self.local_time_type.offset);
+            changes.push((start, true));
+            changes.push((end, false));
+        }
+
+        // When the two changes happen at the same instant, such as the end
+        // of one year’s DST and the start of the next year’s in a zone that
+        // observes it all year round, only the later one counts.
+        changes.sort_by_key(|c| c.0);
+        let mut deduped: Vec<(i64, bool)> = Vec::with_capacity(changes.len());
+        for change in changes {
+            match deduped.last_mut() {
+                Some(last) if last.0 == change.0  => *last = change,
+                _                                 => deduped.push(change),
+            }
+        }
+
+        deduped.dedup_by_key(|c| c.1);
+        deduped
+    }
+}
+
+impl RuleDate {
+
+    /// Returns the number of days since the Unix epoch of this date in the
+    /// given year.
+    fn to_days(self, year: i64) -> i64 {
+        let new_year = days_from_civil(year, 1, 1);
+
+        match self {
+            RuleDate::Julian(day) => {
+                let leap_day = if day >= 60 && days_in_month(year, 2) == 29 { 1 } else { 0 };
+                new_year + i64::from(day) - 1 + leap_day
+            },
+
+            RuleDate::ZeroBased(day) => new_year + i64::from(day),
+
+            RuleDate::MonthWeekDay { month, week: 5, weekday } => {
+                DaySpec::Last(weekday).to_days(year, month)
+            },
+
+            RuleDate::MonthWeekDay { month, week, weekday } => {
+                DaySpec::OnOrAfter(weekday, 1 + 7 * (week - 1)).to_days(year, month)
+            },
+        }
+    }
+}
+
+/// Returns roughly which year a timestamp falls in, which is never more
+/// than a year out.
+fn estimate_year(timestamp: i64) -> i64 {
+    1970 + timestamp.div_euclid(SECONDS_PER_YEAR)
+}
+
+
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when an abbreviation is missing, too short, or has an
+    /// unterminated angle bracket.
+    InvalidAbbreviation,
+
+    /// The error when an offset is missing or out of range.
+    InvalidOffset,
+
+    /// The error when the dates or times of a daylight-saving time rule
+    /// can’t be parsed.
+    InvalidRule,
+
+    /// The error when there are characters left over after the end of the
+    /// string.
+    TrailingCharacters,
+
+    /// The error when a range of time is too long to work out a rule’s
+    /// transitions over.
+    RangeTooLarge,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidAbbreviation  => "invalid abbreviation",
+            Error::InvalidOffset        => "invalid offset",
+            Error::InvalidRule          => "invalid rule",
+            Error::TrailingCharacters   => "trailing characters",
+            Error::RangeTooLarge        => "range too large",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidAbbreviation  => write!(f, "invalid abbreviation in TZ string"),
+            Error::InvalidOffset        => write!(f, "invalid offset in TZ string"),
+            Error::InvalidRule          => write!(f, "invalid daylight-saving time rule in TZ string"),
+            Error::TrailingCharacters   => write!(f, "trailing characters after TZ string"),
+            Error::RangeTooLarge        => write!(f, "range covers more than {} years of a daylight-saving time rule", MAX_YEARS),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        let tz = parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(tz.std.offset, 3600);
+
+        let dst = tz.dst.unwrap();
+        assert_eq!((dst.local_time_type.name.as_str(), dst.local_time_type.offset), ("CEST", 7200));
+        assert_eq!(dst.start, RuleDate::MonthWeekDay { month: 3, week: 5, weekday: 0 });
+        assert_eq!((dst.start_time, dst.end_time), (7200, 10800));
+    }
+
+    #[test]
+    fn parses_extensions() {
+        let tz = parse("<+0330>-3:30").unwrap();
+        assert_eq!((tz.std.name.as_str(), tz.std.offset), ("+0330", 12600));
+        assert!(tz.dst.is_none());
+
+        let tz = parse("<-03>3<-02>,M3.5.0/-2,M10.5.0/-1").unwrap();
+        assert_eq!(tz.dst.unwrap().start_time, -7200);
+
+        let tz = parse("<Z>0").unwrap();
+        assert_eq!(tz.std.name, "Z");
+
+        let tz = parse("EST5EDT").unwrap();
+        assert_eq!(tz.dst.unwrap().end, RuleDate::MonthWeekDay { month: 11, week: 1, weekday: 0 });
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("").is_err());
+        assert!(parse("AB1").is_err());
+        assert!(parse("<>1").is_err());
+        assert!(parse("CET").is_err());
+        assert!(parse("CET-1CEST,M13.5.0,M10.5.0").is_err());
+        assert!(parse("CET-1 ").is_err());
+    }
+
+    #[test]
+    fn transitions() {
+        let tz = parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+
+        // 2021
+        let transitions = tz.transitions(1_609_459_200 .. 1_640_995_200);
+        assert_eq!(transitions.len(), 2);
+        assert_eq!((transitions[0].0, transitions[0].1.is_dst), (1_616_893_200, true));
+        assert_eq!((transitions[1].0, transitions[1].1.is_dst), (1_635_642_000, false));
+
+        assert!(!tz.local_time_type_at(1_616_893_199).is_dst);
+        assert!(tz.local_time_type_at(1_616_893_200).is_dst);
+    }
+
+    #[test]
+    fn southern_hemisphere() {
+        let tz = parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+
+        // 2021-01-01 and 2021-07-01
+        assert!(tz.local_time_type_at(1_609_459_200).is_dst);
+        assert!(!tz.local_time_type_at(1_625_097_600).is_dst);
+    }
+
+    #[test]
+    fn all_year_dst() {
+        let tz = parse("EST5EDT,0/0,J365/25").unwrap();
+        assert!(tz.transitions(1_609_459_200 .. 1_640_995_200).is_empty());
+        assert_eq!(tz.local_time_type_at(1_625_097_600).offset, -14400);
+    }
+
+    #[test]
+    fn julian_days() {
+        // J60 is 1 March, even in a leap year; 59 is 29 February in one.
+        assert_eq!(RuleDate::Julian(60).to_days(2020), days_from_civil(2020, 3, 1));
+        assert_eq!(RuleDate::ZeroBased(59).to_days(2020), days_from_civil(2020, 2, 29));
+    }
+}
@@ -0,0 +1,292 @@
+//! Parsing of the `zone.tab`, `zone1970.tab`, and `iso3166.tab` files
+//!
+//! Alongside the compiled zone files, a zoneinfo tree contains tables of
+//! metadata about them: which countries each zone covers, where its
+//! principal location is, and any comments needed to tell apart the zones
+//! within a country. These tables are tab-separated text files:
+//!
+//! - `zone.tab` has one line per country and zone, with a single country
+//!   code in its first column;
+//! - `zone1970.tab` has one line per zone, with every country it covers in
+//!   its first column, separated by commas;
+//! - `iso3166.tab` maps each country code to the country's name.
+//!
+//! This module parses these tables, and allows looking up zones by country.
+
+use std::collections::BTreeMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::result;
+
+use super::Result;
+
+
+/// A line from `zone.tab` or `zone1970.tab`, describing a zone.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZoneEntry {
+
+    /// The ISO 3166 alpha-2 codes of the countries this zone covers, such
+    /// as "GB". Lines from `zone.tab` only ever have one.
+    pub country_codes: Vec<String>,
+
+    /// The position of the zone’s principal location.
+    pub coordinates: Coordinates,
+
+    /// The name of the zone, such as "Europe/London".
+    pub zone_name: String,
+
+    /// Comments describing the area this zone covers, if the country has
+    /// more than one zone.
+    pub comments: Option<String>,
+}
+
+
+/// The position of a location, in degrees.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Coordinates {
+
+    /// The latitude, which is positive in the northern hemisphere.
+    pub latitude: f64,
+
+    /// The longitude, which is positive east of Greenwich.
+    pub longitude: f64,
+}
+
+impl Coordinates {
+
+    /// Decodes a pair of coordinates in the ISO 6709 sign-degrees-minutes
+    /// format used by the tables, such as `+5130-00007`, or with seconds,
+    /// `+513030-0000731`. Returns `None` if the input is invalid.
+    pub fn from_iso6709(input: &str) -> Option<Coordinates> {
+        let split = input.get(1 ..)?.find(['+', '-'])? + 1;
+        let (latitude, longitude) = input.split_at(split);
+
+        Some(Coordinates {
+            latitude:   parse_degrees(latitude, 2)?,
+            longitude:  parse_degrees(longitude, 3)?,
+        })
+    }
+}
+
+/// Decodes a signed number of degrees, minutes, and (optionally) seconds,
+/// where the degrees take up the given number of digits.
+fn parse_degrees(input: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match input.as_bytes().first() {
+        Some(b'+')  => 1.0,
+        Some(b'-')  => -1.0,
+        _           => return None,
+    };
+
+    let digits = &input[1 ..];
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let (degrees, minutes, seconds) = if digits.len() == degree_digits + 2 {
+        (&digits[.. degree_digits], &digits[degree_digits ..], "0")
+    }
+    else if digits.len() == degree_digits + 4 {
+        (&digits[.. degree_digits], &digits[degree_digits .. degree_digits + 2], &digits[degree_digits + 2 ..])
+    }
+    else {
+        return None;
+    };
+
+    let degrees: f64 = degrees.parse().ok()?;
+    let minutes: f64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    Some(sign * (degrees + minutes / 60.0 + seconds / 3600.0))
+}
+
+
+/// The metadata tables from a zoneinfo tree.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ZoneMetadata {
+
+    /// Every zone from the zone table, in the order they appear.
+    pub zones: Vec<ZoneEntry>,
+
+    /// The name of each country, keyed by its ISO 3166 alpha-2 code.
+    pub countries: BTreeMap<String, String>,
+}
+
+impl ZoneMetadata {
+
+    /// Reads the metadata tables from the zoneinfo tree under the given
+    /// root, such as `/usr/share/zoneinfo`.
+    ///
+    /// This reads `zone.tab`, which lists the zones of every country
+    /// separately, making it suitable for picking a zone by country; if it
+    /// doesn’t exist, `zone1970.tab` is read instead.
+    pub fn from_zoneinfo<P: AsRef<Path>>(root: P) -> Result<ZoneMetadata> {
+        let root = root.as_ref();
+
+        let zone_tab = match fs::read_to_string(root.join("zone.tab")) {
+            Ok(contents)  => contents,
+            Err(_)        => fs::read_to_string(root.join("zone1970.tab"))?,
+        };
+
+        let iso3166_tab = fs::read_to_string(root.join("iso3166.tab"))?;
+
+        Ok(ZoneMetadata {
+            zones:      parse_zone_tab(&zone_tab)?,
+            countries:  parse_iso3166_tab(&iso3166_tab)?,
+        })
+    }
+
+    /// Returns the zones that cover the country with the given code.
+    pub fn zones_for_country(&self, country_code: &str) -> Vec<&ZoneEntry> {
+        self.zones.iter()
+                  .filter(|z| z.country_codes.iter().any(|c| c == country_code))
+                  .collect()
+    }
+
+    /// Returns the entry for the zone with the given name, or `None` if it
+    /// isn’t in the table.
+    pub fn zone(&self, zone_name: &str) -> Option<&ZoneEntry> {
+        self.zones.iter().find(|z| z.zone_name == zone_name)
+    }
+
+    /// Returns the name of the country with the given code, such as
+    /// "Britain (UK)" for "GB".
+    pub fn country_name(&self, country_code: &str) -> Option<&str> {
+        self.countries.get(country_code).map(|s| &**s)
+    }
+}
+
+
+/// Parses the contents of a `zone.tab` or `zone1970.tab` file.
+pub fn parse_zone_tab(input: &str) -> Result<Vec<ZoneEntry>> {
+    let mut zones = Vec::new();
+
+    for (index, fields) in table_lines(input) {
+        let invalid = || Error::InvalidLine { line_number: index + 1 };
+
+        if fields.len() < 3 || fields.len() > 4 {
+            return Err(invalid().into());
+        }
+
+        let coordinates = Coordinates::from_iso6709(fields[1]).ok_or_else(invalid)?;
+
+        zones.push(ZoneEntry {
+            country_codes:  fields[0].split(',').map(str::to_owned).collect(),
+            coordinates,
+            zone_name:      fields[2].to_owned(),
+            comments:       fields.get(3).map(|c| (*c).to_owned()),
+        });
+    }
+
+    Ok(zones)
+}
+
+/// Parses the contents of an `iso3166.tab` file into a map of country codes
+/// to country names.
+pub fn parse_iso3166_tab(input: &str) -> Result<BTreeMap<String, String>> {
+    let mut countries = BTreeMap::new();
+
+    for (index, fields) in table_lines(input) {
+        if fields.len() != 2 {
+            return Err(Error::InvalidLine { line_number: index + 1 }.into());
+        }
+
+        countries.insert(fields[0].to_owned(), fields[1].to_owned());
+    }
+
+    Ok(countries)
+}
+
+/// Splits a table into the tab-separated fields of each line, skipping
+/// blank lines and comments.
+fn table_lines(input: &str) -> impl Iterator<Item=(usize, Vec<&str>)> {
+    input.lines()
+         .enumerate()
+         .filter(|&(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+         .map(|(index, line)| (index, line.split('\t').collect()))
+}
+
+
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when a line couldn’t be parsed.
+    InvalidLine {
+
+        /// The number of the line that couldn’t be parsed, starting at 1.
+        line_number: usize,
+    },
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidLine { .. } => "invalid line",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidLine { line_number } => write!(f, "invalid line (line {})", line_number),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn coordinates() {
+        let coords = Coordinates::from_iso6709("+5130-00007").unwrap();
+        assert_eq!(coords.latitude, 51.5);
+        assert!((coords.longitude - -0.116_666).abs() < 0.000_001);
+
+        let coords = Coordinates::from_iso6709("-690022+0393524").unwrap();
+        assert!((coords.latitude - -69.006_111).abs() < 0.000_001);
+        assert!((coords.longitude - 39.59).abs() < 0.000_001);
+
+        assert_eq!(Coordinates::from_iso6709("+5130"), None);
+        assert_eq!(Coordinates::from_iso6709("+513-00007"), None);
+    }
+
+    #[test]
+    fn zone_tab() {
+        let input = "\
+# tzdb timezone descriptions
+#
+AE,OM,RE,SC,TF\t+2518+05518\tAsia/Dubai\tCrozet
+GB,GG,IM,JE\t+513030-0000731\tEurope/London
+";
+
+        let zones = parse_zone_tab(input).unwrap();
+        assert_eq!(zones.len(), 2);
+        assert_eq!(zones[0].country_codes, vec![ "AE", "OM", "RE", "SC", "TF" ]);
+        assert_eq!(zones[0].comments, Some("Crozet".to_owned()));
+        assert_eq!(zones[1].zone_name, "Europe/London");
+        assert_eq!(zones[1].comments, None);
+    }
+
+    #[test]
+    fn by_country() {
+        let metadata = ZoneMetadata {
+            zones: parse_zone_tab("GB\t+513030-0000731\tEurope/London\nIM\t+5409-00428\tEurope/Isle_of_Man\n").unwrap(),
+            countries: parse_iso3166_tab("# comment\nGB\tBritain (UK)\nIM\tIsle of Man\n").unwrap(),
+        };
+
+        let zones = metadata.zones_for_country("IM");
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].zone_name, "Europe/Isle_of_Man");
+        assert_eq!(metadata.country_name("GB"), Some("Britain (UK)"));
+        assert!(metadata.zone("Europe/London").is_some());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_zone_tab("GB\tLondon\n").is_err());
+        assert!(parse_iso3166_tab("GB\n").is_err());
+    }
+}
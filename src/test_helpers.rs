@@ -0,0 +1,36 @@
+//! Helpers shared by the tests of several modules
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use {Indicators, LocalTimeType};
+
+
+/// A version 1 file for `EST`, as zic used to compile it: one local time
+/// type, five hours behind UTC, and no transitions.
+pub(crate) const EST: &[u8] = &[
+    0x54, 0x5A, 0x69, 0x66, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x04, 0xFF, 0xFF, 0xB9, 0xB0,
+    0x00, 0x00, 0x45, 0x53, 0x54, 0x00, 0x00, 0x00,
+];
+
+/// A local time type without any indicators.
+pub(crate) fn ltt(offset: i64, is_dst: bool, name: &str) -> LocalTimeType {
+    LocalTimeType { name: name.to_owned(), offset, is_dst, indicators: Indicators::UNSPECIFIED }
+}
+
+/// Creates an empty directory to use as a zoneinfo tree, named after the
+/// module and the test using it, so tests running at the same time don’t
+/// share one.
+pub(crate) fn empty_tree(module: &str, test: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("zoneinfo_compiled-{}-{}-{}", module, test, process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
@@ -0,0 +1,69 @@
+//! Walking a tree of compiled zoneinfo files
+//!
+//! A zoneinfo tree, such as `/usr/share/zoneinfo`, contains one compiled
+//! file per zone, named after the zone -- but it also contains text files
+//! such as `zone.tab`, and whole copies of itself under `posix/` and
+//! `right/`. This module finds just the zone files.
+//!
+//! This module only uses the standard library, as it also gets included
+//! into the build script.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+
+/// Directories that contain copies of the tree, rather than more zones.
+const SKIPPED_DIRECTORIES: &[&str] = &[ "posix", "right" ];
+
+/// Compiled files that are not zones in their own right.
+const SKIPPED_FILES: &[&str] = &[ "localtime", "posixrules" ];
+
+
+/// Returns the names of every zone in the tree under the given root, such
+/// as "Europe/London", in order.
+///
+/// Zones are found by their contents, rather than their names: any regular
+/// file (or symbolic link to one) that starts with the `TZif` magic number
+/// counts. Symbolic links to directories are not followed.
+pub(crate) fn zone_names(root: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    walk(root, "", &mut names)?;
+    names.sort();
+    Ok(names)
+}
+
+fn walk(dir: &Path, prefix: &str, names: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = match entry.file_name().into_string() {
+            Ok(file_name) => file_name,
+            Err(_)        => continue,
+        };
+
+        let name = format!("{}{}", prefix, file_name);
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if !(prefix.is_empty() && SKIPPED_DIRECTORIES.contains(&&*file_name)) {
+                walk(&entry.path(), &format!("{}/", name), names)?;
+            }
+        }
+        else if !(prefix.is_empty() && SKIPPED_FILES.contains(&&*file_name)) && is_zone_file(&entry.path()) {
+            names.push(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the file at the given path is a compiled zone file. Anything
+/// that can’t be read, such as a broken link, isn’t.
+fn is_zone_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+
+    match fs::File::open(path) {
+        Ok(mut file)  => file.read_exact(&mut magic).is_ok() && magic == *b"TZif",
+        Err(_)        => false,
+    }
+}
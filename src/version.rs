@@ -0,0 +1,234 @@
+//! Finding out which release of the time zone database is installed
+//!
+//! Releases of the time zone database are named after the year and a
+//! letter, such as `2024a`, and a zoneinfo tree can say which one it was
+//! compiled from in a few places: the `+VERSION` file that the tz
+//! distribution installs at its root, the `# version` line at the top of
+//! `tzdata.zi`, which distributions that leave out `+VERSION` still ship,
+//! or the header of Android’s `tzdata` file. This module reads whichever
+//! of them there is, and compares the versions it finds.
+
+use std::cmp::Ordering;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::result;
+
+use {android, zi};
+use Result;
+
+
+/// The file at the root of a zoneinfo tree that gives its version.
+const VERSION_FILE: &str = "+VERSION";
+
+/// The source file that can sit at the root of a zoneinfo tree, which gives
+/// its version in a comment near the top.
+const ZI_FILE: &str = "tzdata.zi";
+
+/// The name of Android’s file of compiled zones.
+const ANDROID_FILE: &str = "tzdata";
+
+
+/// The version of a release of the time zone database, such as `2024a`.
+///
+/// Versions are ordered by release, so `2024a` comes before `2024b`, which
+/// comes before `2025a`. A version with a suffix, such as
+/// `2024a-12-g0123abc` for a build from the repository in between releases,
+/// comes after the release it starts with.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Version {
+
+    /// The year of the release, such as 2024.
+    pub year: u16,
+
+    /// The letters that count the releases in that year, such as “a” for
+    /// the first one. After “z” comes “za”.
+    pub release: String,
+
+    /// Anything after the release, which is empty for a release itself.
+    pub suffix: String,
+}
+
+impl Version {
+
+    /// The number of commits after the release, for a version described
+    /// by `git describe`, such as 12 for `2024a-12-g0123abc`.
+    fn commits(&self) -> Option<u32> {
+        self.suffix.strip_prefix('-')?.split('-').next()?.parse().ok()
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        self.year.cmp(&other.year)
+            .then_with(|| self.release.len().cmp(&other.release.len()))
+            .then_with(|| self.release.cmp(&other.release))
+            .then_with(|| self.suffix.is_empty().cmp(&other.suffix.is_empty()).reverse())
+            .then_with(|| self.commits().cmp(&other.commits()))
+            .then_with(|| self.suffix.cmp(&other.suffix))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "{}{}{}", self.year, self.release, self.suffix)
+    }
+}
+
+
+/// Parses a version, such as `2024a`, ignoring any whitespace around it.
+pub fn parse(input: &str) -> Result<Version> {
+    let input = input.trim();
+    let invalid = || Error::InvalidVersion(input.to_owned());
+
+    let year_end = input.bytes().position(|c| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    if year_end != 4 {
+        return Err(Box::new(invalid()));
+    }
+
+    let rest = &input[year_end ..];
+    let release_end = rest.bytes().position(|c| !c.is_ascii_lowercase()).unwrap_or(rest.len());
+    if release_end == 0 {
+        return Err(Box::new(invalid()));
+    }
+
+    Ok(Version {
+        year: input[.. year_end].parse()?,
+        release: rest[.. release_end].to_owned(),
+        suffix: rest[release_end ..].to_owned(),
+    })
+}
+
+/// Reads the version of the zoneinfo tree under the given root, from its
+/// `+VERSION` file, the `# version` line of its `tzdata.zi` file, or the
+/// header of an Android `tzdata` file, in that order. The root can also be
+/// an Android `tzdata` file itself.
+///
+/// The first of these that exists has to give a valid version, and it’s an
+/// error if none of them do.
+pub fn read<R: AsRef<Path>>(root: R) -> Result<Version> {
+    let root = root.as_ref();
+
+    if root.is_file() {
+        return read_android(root);
+    }
+
+    if let Ok(contents) = fs::read_to_string(root.join(VERSION_FILE)) {
+        return parse(&contents);
+    }
+
+    if let Ok(file) = File::open(root.join(ZI_FILE)) {
+        return match zi::read_version(BufReader::new(file))? {
+            Some(version) => parse(&version),
+            None          => Err(Box::new(Error::NotFound)),
+        };
+    }
+
+    if root.join(ANDROID_FILE).is_file() {
+        return read_android(&root.join(ANDROID_FILE));
+    }
+
+    Err(Box::new(Error::NotFound))
+}
+
+/// Reads the version from the header of an Android `tzdata` file.
+fn read_android(path: &Path) -> Result<Version> {
+    let mut magic = [0u8; 12];
+    File::open(path)?.read_exact(&mut magic)?;
+
+    match android::header_version(&magic) {
+        Some(version) => parse(&version),
+        None          => Err(Box::new(android::Error::InvalidHeader)),
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Error {
+
+    /// The error when a version isn’t four digits of a year followed by the
+    /// letters of a release.
+    InvalidVersion(String),
+
+    /// The error when a zoneinfo tree doesn’t say which version it is.
+    NotFound,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidVersion(_)  => "invalid version",
+            Error::NotFound           => "version not found",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidVersion(ref version)  => write!(f, "invalid version {:?}", version),
+            Error::NotFound                     => write!(f, "no version found"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::empty_tree;
+
+    #[test]
+    fn parsing() {
+        let version = parse("2024a\n").unwrap();
+        assert_eq!(version, Version { year: 2024, release: "a".to_owned(), suffix: String::new() });
+        assert_eq!(version.to_string(), "2024a");
+
+        let version = parse("2025b-12-g0123abc-dirty").unwrap();
+        assert_eq!(version.suffix, "-12-g0123abc-dirty");
+        assert_eq!(version.to_string(), "2025b-12-g0123abc-dirty");
+
+        for invalid in &[ "", "2024", "24a", "20245a", "a2024", "2024A", "unknown" ] {
+            assert!(parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let versions = [ "2016j", "2023c", "2024a", "2024a-2-g0123abc", "2024a-12-g4567def", "2024b", "2024z", "2024za", "2025a" ];
+        for pair in versions.windows(2) {
+            assert!(parse(pair[0]).unwrap() < parse(pair[1]).unwrap(), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn sources() {
+        let root = empty_tree("version", "sources");
+        assert!(read(&root).is_err());
+
+        let mut android = b"tzdata2023c\0".to_vec();
+        android.extend_from_slice(&[0; 12]);
+        fs::write(root.join("tzdata"), &android).unwrap();
+        assert_eq!(read(&root).unwrap().to_string(), "2023c");
+        assert_eq!(read(root.join("tzdata")).unwrap().to_string(), "2023c");
+
+        fs::write(root.join("tzdata.zi"), "# version 2024b\n# This zic input file is in the public domain.\nR d 1916 o - Jun 14 23s 1 S\n").unwrap();
+        assert_eq!(read(&root).unwrap().to_string(), "2024b");
+
+        fs::write(root.join("+VERSION"), "2025a\n").unwrap();
+        assert_eq!(read(&root).unwrap().to_string(), "2025a");
+
+        fs::write(root.join("+VERSION"), "unknown\n").unwrap();
+        assert!(read(&root).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
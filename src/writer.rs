@@ -0,0 +1,306 @@
+//! Writing time zone files
+//!
+//! This module does the opposite of the parser: `uncook` turns interpreted
+//! zone data back into the internal structure of a zoneinfo file, and
+//! `write` turns that structure into bytes. Data with a version of 2 or
+//! later is written with both data blocks and its footer, so any program
+//! that reads compiled zoneinfo files can read it.
+
+use std::convert::TryFrom;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::result;
+
+use super::{Result, TZData};
+use parser;
+
+
+/// The most abbreviation characters a file can use, as each local time type
+/// refers to its abbreviation with a single byte.
+const MAX_ABBREVIATION_CHARS: usize = 256;
+
+/// The most local time types a file can have, as each transition refers to
+/// its local time type with a single byte.
+const MAX_LOCAL_TIME_TYPES: usize = 256;
+
+
+/// Turns a set of interpreted time zone data back into the internal
+/// structure of a version 2 zoneinfo file.
+///
+/// Each local time type keeps its index, and local time types with the
+/// same abbreviation share its characters. A set of indicators is only
+/// written if at least one local time type specifies it.
+pub fn uncook(tz: &TZData) -> Result<parser::TZData> {
+    if tz.local_time_types.len() > MAX_LOCAL_TIME_TYPES {
+        return Err(Box::new(Error::TooManyLocalTimeTypes));
+    }
+
+    let mut time_info = Vec::with_capacity(tz.local_time_types.len());
+    let mut standard_flags = Vec::with_capacity(tz.local_time_types.len());
+    let mut gmt_flags = Vec::with_capacity(tz.local_time_types.len());
+    let mut names: Vec<(&str, usize)> = Vec::new();
+    let mut strings: Vec<u8> = Vec::new();
+
+    for ltt in &tz.local_time_types {
+        let offset = i32::try_from(ltt.offset)
+                         .map_err(|_| Error::OffsetOutOfRange { offset: ltt.offset })?;
+
+        let name_offset = match names.iter().find(|n| n.0 == ltt.name) {
+            Some(&(_, position)) => position,
+            None => {
+                if strings.len() + ltt.name.len() + 1 > MAX_ABBREVIATION_CHARS {
+                    return Err(Box::new(Error::TooManyAbbreviationChars));
+                }
+
+                let position = strings.len();
+                names.push((&ltt.name, position));
+                strings.extend_from_slice(ltt.name.as_bytes());
+                strings.push(0);
+                position
+            },
+        };
+
+        time_info.push(parser::LocalTimeTypeData {
+            offset,
+            is_dst:       ltt.is_dst as u8,
+            name_offset:  name_offset as u8,
+        });
+
+        standard_flags.push(ltt.indicators.is_std.unwrap_or(false) as u8);
+        gmt_flags.push(ltt.indicators.is_ut.unwrap_or(false) as u8);
+    }
+
+    if tz.local_time_types.iter().all(|ltt| ltt.indicators.is_std.is_none()) {
+        standard_flags.clear();
+    }
+
+    if tz.local_time_types.iter().all(|ltt| ltt.indicators.is_ut.is_none()) {
+        gmt_flags.clear();
+    }
+
+    let transitions: Vec<_> = tz.transitions.iter().map(|t| {
+        parser::TransitionData {
+            timestamp: t.timestamp,
+            local_time_type_index: t.local_time_type as u8,
+        }
+    }).collect();
+
+    let leap_seconds: Vec<_> = tz.leap_seconds.iter().map(|ls| {
+        parser::LeapSecondData {
+            timestamp: ls.timestamp,
+            leap_second_count: ls.leap_second_count,
+        }
+    }).collect();
+
+    let header = parser::Header {
+        version:               b'2',
+        num_gmt_flags:         gmt_flags.len() as u32,
+        num_standard_flags:    standard_flags.len() as u32,
+        num_leap_seconds:      leap_seconds.len() as u32,
+        num_transitions:       transitions.len() as u32,
+        num_local_time_types:  time_info.len() as u32,
+        num_abbr_chars:        strings.len() as u32,
+    };
+
+    Ok(parser::TZData {
+        header,
+        transitions,
+        time_info,
+        leap_seconds,
+        strings,
+        standard_flags,
+        gmt_flags,
+        footer: tz.footer.clone(),
+    })
+}
+
+
+/// Writes the internal structure of a zoneinfo file as bytes.
+///
+/// The version is taken from the header, but the counts are taken from the
+/// structures themselves. If the version is 2 or later, the first data
+/// block only contains the transitions and leap seconds whose times fit in
+/// 32 bits, as older readers expect.
+pub fn write(tz: &parser::TZData) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let wide = tz.header.version >= b'2';
+
+    write_block(&mut buf, tz, false);
+
+    if wide {
+        write_block(&mut buf, tz, true);
+        buf.push(b'\n');
+        buf.extend_from_slice(tz.footer.as_ref().map_or(&b""[..], |f| f.as_bytes()));
+        buf.push(b'\n');
+    }
+
+    buf
+}
+
+/// Writes interpreted time zone data as the bytes of a version 2 zoneinfo
+/// file.
+pub fn to_bytes(tz: &TZData) -> Result<Vec<u8>> {
+    Ok(write(&uncook(tz)?))
+}
+
+/// Writes a header and data block, using 64-bit times if `wide` is set,
+/// and leaving out any times that don’t fit in 32 bits otherwise.
+fn write_block(buf: &mut Vec<u8>, tz: &parser::TZData, wide: bool) {
+    let fits = |timestamp: i64| wide || i32::try_from(timestamp).is_ok();
+    let transitions: Vec<_> = tz.transitions.iter().filter(|t| fits(t.timestamp)).collect();
+    let leap_seconds: Vec<_> = tz.leap_seconds.iter().filter(|ls| fits(ls.timestamp)).collect();
+
+    let write_timestamp = |buf: &mut Vec<u8>, timestamp: i64| {
+        if wide {
+            buf.extend_from_slice(&timestamp.to_be_bytes());
+        }
+        else {
+            buf.extend_from_slice(&(timestamp as i32).to_be_bytes());
+        }
+    };
+
+    buf.extend_from_slice(b"TZif");
+    buf.push(tz.header.version);
+    buf.extend_from_slice(&[0; 15]);
+
+    for &count in &[ tz.gmt_flags.len(), tz.standard_flags.len(), leap_seconds.len(),
+                     transitions.len(), tz.time_info.len(), tz.strings.len() ] {
+        buf.extend_from_slice(&(count as u32).to_be_bytes());
+    }
+
+    for t in &transitions {
+        write_timestamp(buf, t.timestamp);
+    }
+
+    for t in &transitions {
+        buf.push(t.local_time_type_index);
+    }
+
+    for info in &tz.time_info {
+        buf.extend_from_slice(&info.offset.to_be_bytes());
+        buf.push(info.is_dst);
+        buf.push(info.name_offset);
+    }
+
+    buf.extend_from_slice(&tz.strings);
+
+    for ls in &leap_seconds {
+        write_timestamp(buf, ls.timestamp);
+        buf.extend_from_slice(&ls.leap_second_count.to_be_bytes());
+    }
+
+    buf.extend_from_slice(&tz.standard_flags);
+    buf.extend_from_slice(&tz.gmt_flags);
+}
+
+
+#[derive(Debug, Copy, Clone)]
+pub enum Error {
+
+    /// The error when an offset doesn’t fit in the 32 bits a file has for
+    /// it.
+    OffsetOutOfRange {
+
+        /// The offset that was out of range, in seconds.
+        offset: i64,
+    },
+
+    /// The error when there are more distinct local time types than a file
+    /// can refer to.
+    TooManyLocalTimeTypes,
+
+    /// The error when the abbreviations take up more characters than a file
+    /// can refer to.
+    TooManyAbbreviationChars,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::OffsetOutOfRange { .. }     => "offset out of range",
+            Error::TooManyLocalTimeTypes       => "too many local time types",
+            Error::TooManyAbbreviationChars    => "too many abbreviation chars",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::OffsetOutOfRange { offset }  => write!(f, "offset of {} seconds is out of range", offset),
+            Error::TooManyLocalTimeTypes        => write!(f, "too many local time types (limit is {})", MAX_LOCAL_TIME_TYPES),
+            Error::TooManyAbbreviationChars     => write!(f, "too many abbreviation chars (limit is {})", MAX_ABBREVIATION_CHARS),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_helpers::ltt;
+    use {Indicators, TimeType};
+
+    fn paris() -> TZData {
+        let mut tz = TZData::from_timespans(ltt(561, false, "LMT"), vec![
+            (-2_486_592_561, ltt(561, false, "PMT")),
+            (-1_855_958_961, ltt(0, false, "WET")),
+            (  354_675_600, ltt(7200, true, "CEST")),
+            (  370_400_400, ltt(3600, false, "CET")),
+            (4_133_984_400, ltt(7200, true, "CEST")),
+        ]);
+
+        tz.footer = Some("CET-1CEST,M3.5.0,M10.5.0/3".to_owned());
+        tz
+    }
+
+    #[test]
+    fn types() {
+        let raw = uncook(&paris()).unwrap();
+        assert_eq!(raw.header.version, b'2');
+        assert_eq!(raw.time_info.len(), 5);
+        assert_eq!(raw.time_info[0].name_offset, 0);
+        assert_eq!(raw.strings, b"LMT\0PMT\0WET\0CEST\0CET\0".to_vec());
+        assert_eq!(raw.transitions[4].local_time_type_index, 3);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = to_bytes(&paris()).unwrap();
+        let raw = parser::parse(bytes, parser::Limits::sensible()).unwrap();
+        assert_eq!(raw.header.version, b'2');
+        assert_eq!(super::super::cook(raw).unwrap(), paris());
+    }
+
+    #[test]
+    fn version_1() {
+        let mut raw = uncook(&paris()).unwrap();
+        raw.header.version = 0;
+
+        let parsed = parser::parse(write(&raw), parser::Limits::sensible()).unwrap();
+        assert_eq!(parsed.transitions.len(), 3);
+        assert_eq!(parsed.footer, None);
+    }
+
+    #[test]
+    fn indicators() {
+        let mut tz = paris();
+        assert!(uncook(&tz).unwrap().standard_flags.is_empty());
+
+        tz.local_time_types[3].indicators = Indicators::from(TimeType::UTC);
+        let raw = uncook(&tz).unwrap();
+        assert_eq!(raw.standard_flags, vec![ 0, 0, 0, 1, 0 ]);
+        assert_eq!(raw.gmt_flags, vec![ 0, 0, 0, 1, 0 ]);
+
+        let cooked = super::super::cook(parser::parse(write(&raw), parser::Limits::sensible()).unwrap()).unwrap();
+        assert_eq!(cooked.local_time_types[3].transition_type(), TimeType::UTC);
+        assert_eq!(cooked.local_time_types[0].indicators, Indicators { is_std: Some(false), is_ut: Some(false) });
+    }
+
+    #[test]
+    fn offset_out_of_range() {
+        let mut tz = paris();
+        tz.local_time_types[0].offset = 1 << 40;
+        assert!(uncook(&tz).is_err());
+    }
+}
@@ -0,0 +1,1160 @@
+//! Parsing of the `tzdata.zi` single-file distribution
+//!
+//! Many systems ship a file called `tzdata.zi` alongside their compiled
+//! zoneinfo files. This is a condensed text form of the *entire* time zone
+//! database -- every `Rule`, `Zone`, and `Link` line -- in the same input
+//! format that `zic` reads, with keywords abbreviated and comments removed.
+//!
+//! This module reads that file into a map of zone names to zone lines, and
+//! can compile an individual zone into a `TZData` value, giving an
+//! alternative to reading each zone's compiled file separately.
+//!
+//! For more information on the input format, see
+//! [man 8 zic](https://data.iana.org/time-zones/tzdb/zic.8.txt).
+
+use std::collections::BTreeMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+use std::result;
+
+use super::{Indicators, LocalTimeType, Result, TimeType, TZData};
+use posix;
+
+
+/// The last year that transitions get generated for when a zone's rules
+/// continue indefinitely. This matches the range of a compiled file's
+/// 32-bit data.
+const LAST_YEAR: i64 = 2037;
+
+/// The earliest year that a rule with a `FROM` field of `minimum` gets
+/// expanded from.
+const FIRST_YEAR: i64 = 1800;
+
+/// The furthest year from year 0, in either direction, that a line can
+/// refer to. This is far beyond anything in the database, and keeps the
+/// times worked out from these years well within range.
+const MAX_YEAR: i64 = 1_000_000;
+
+/// The largest number of hours that a time of day, offset, or amount of
+/// daylight-saving time can have, for the same reason.
+const MAX_HOURS: i64 = 1_000_000;
+
+/// The start of the comment at the top of the file that gives its version.
+const VERSION_PREFIX: &str = "# version ";
+
+
+/// The contents of a `tzdata.zi` file: every rule, zone, and link in the
+/// time zone database.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ZiData {
+
+    /// The release version of the database, such as "2024a", if the
+    /// comments at the top of the file included a `# version` comment.
+    pub version: Option<String>,
+
+    /// The rules described in the file, grouped by the name of the rule set
+    /// they belong to.
+    pub rules: BTreeMap<String, Vec<Rule>>,
+
+    /// The zones described in the file, keyed by their names.
+    pub zones: BTreeMap<String, Vec<ZoneLine>>,
+
+    /// The links described in the file, keyed by the link name, with the
+    /// name of the zone they refer to.
+    pub links: BTreeMap<String, String>,
+}
+
+
+/// A line from a rule set, describing a daylight-saving change that occurs
+/// once in each of a range of years.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rule {
+
+    /// The first year in which the rule applies.
+    pub from_year: i64,
+
+    /// The last year in which the rule applies, or `None` if it continues
+    /// to apply indefinitely.
+    pub to_year: Option<i64>,
+
+    /// The month in which the rule takes effect, from 1 to 12.
+    pub month: u8,
+
+    /// The day of the month on which the rule takes effect.
+    pub day: DaySpec,
+
+    /// The time of day at which the rule takes effect.
+    pub time: TimeSpec,
+
+    /// Number of seconds to be added to standard time while this rule is
+    /// in effect.
+    pub save: i64,
+
+    /// Whether the time while this rule is in effect counts as DST.
+    pub is_dst: bool,
+
+    /// The letters to substitute into a zone's abbreviation format while
+    /// this rule is in effect, such as "S" or "D".
+    pub letters: String,
+}
+
+
+/// A line from a zone, describing the local time in a location up until a
+/// particular instant.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZoneLine {
+
+    /// Number of seconds to be added to Universal Time to get standard time.
+    pub offset: i64,
+
+    /// The daylight-saving rules to apply on top of standard time.
+    pub rules: ZoneRules,
+
+    /// The format of the time zone abbreviation, such as "E%sT", "GMT/BST",
+    /// or "%z".
+    pub format: String,
+
+    /// The instant at which this line stops applying, or `None` if this is
+    /// the last line of the zone.
+    pub until: Option<Until>,
+}
+
+
+/// The daylight-saving rules that a zone line applies.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ZoneRules {
+
+    /// Standard time always applies.
+    None,
+
+    /// A fixed number of seconds is always added to standard time.
+    Save(i64),
+
+    /// The rules in the rule set with this name apply.
+    Named(String),
+}
+
+
+/// The instant at which a zone line stops applying.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Until {
+
+    /// The year of the instant.
+    pub year: i64,
+
+    /// The month of the instant, from 1 to 12.
+    pub month: u8,
+
+    /// The day of the month of the instant.
+    pub day: DaySpec,
+
+    /// The time of day of the instant.
+    pub time: TimeSpec,
+}
+
+
+/// A specification of a day within a month.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DaySpec {
+
+    /// A fixed day of the month, such as `5`.
+    Ordinal(u8),
+
+    /// The last given weekday of the month, such as `lastSun`.
+    Last(u8),
+
+    /// The first given weekday on or after a day, such as `Sun>=8`.
+    OnOrAfter(u8, u8),
+
+    /// The last given weekday on or before a day, such as `Sun<=25`.
+    OnOrBefore(u8, u8),
+}
+
+// Weekdays are stored as a number of days since Sunday, in the same way as
+// `tm_wday` in C.
+
+
+/// A time of day, and the clock it should be read from.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct TimeSpec {
+
+    /// Number of seconds since midnight. This can be negative, or over a
+    /// day long.
+    pub seconds: i64,
+
+    /// Whether the time is on the wall clock, the standard time clock, or
+    /// in Universal Time.
+    pub time_type: TimeType,
+}
+
+
+/// Parses the contents of a `tzdata.zi` file.
+///
+/// Because the format of this file is the same as the input to `zic`, this
+/// can also read uncondensed source files, such as `backward`.
+pub fn parse(input: &str) -> Result<ZiData> {
+    let mut data = ZiData::default();
+
+    // The name of the zone whose continuation lines are being read, if the
+    // previous zone line had an UNTIL field.
+    let mut continuing: Option<String> = None;
+    let mut in_header = true;
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+
+        // The version only counts in the comments at the top of the file,
+        // as with `read_version`.
+        in_header = in_header && line.starts_with('#');
+        if in_header && data.version.is_none() {
+            data.version = version_comment(line).map(str::to_owned);
+        }
+
+        let content = match line.find('#') {
+            Some(pos) => &line[.. pos],
+            None      => line,
+        };
+
+        let fields: Vec<&str> = content.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+
+        let invalid = || Error::InvalidLine { line_number };
+
+        if let Some(name) = continuing.take() {
+            let zone_line = parse_zone_line(&fields).ok_or_else(invalid)?;
+            if zone_line.until.is_some() {
+                continuing = Some(name.clone());
+            }
+
+            data.zones.entry(name).or_default().push(zone_line);
+        }
+        else if keyword_matches(fields[0], "rule") {
+            if fields.len() != 10 {
+                return Err(invalid().into());
+            }
+
+            let rule = parse_rule(&fields[2..]).ok_or_else(invalid)?;
+            data.rules.entry(fields[1].to_owned()).or_default().push(rule);
+        }
+        else if keyword_matches(fields[0], "zone") {
+            if fields.len() < 5 {
+                return Err(invalid().into());
+            }
+
+            let name = fields[1].to_owned();
+            if data.zones.contains_key(&name) {
+                return Err(Error::DuplicateZone(name).into());
+            }
+
+            let zone_line = parse_zone_line(&fields[2..]).ok_or_else(invalid)?;
+            if zone_line.until.is_some() {
+                continuing = Some(name.clone());
+            }
+
+            data.zones.insert(name, vec![ zone_line ]);
+        }
+        else if keyword_matches(fields[0], "link") {
+            if fields.len() != 3 {
+                return Err(invalid().into());
+            }
+
+            data.links.insert(fields[2].to_owned(), fields[1].to_owned());
+        }
+        else {
+            return Err(invalid().into());
+        }
+    }
+
+    if let Some(name) = continuing {
+        return Err(Error::MissingContinuation(name).into());
+    }
+
+    Ok(data)
+}
+
+/// Reads the version from the comments at the top of a `tzdata.zi` file,
+/// stopping at the first line that isn’t one, so the rest of the file
+/// doesn’t get read.
+pub fn read_version<R: BufRead>(reader: R) -> Result<Option<String>> {
+    for line in reader.lines() {
+        let line = line?;
+        if !line.starts_with('#') {
+            break;
+        }
+
+        if let Some(version) = version_comment(&line) {
+            return Ok(Some(version.to_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the version from a `# version` comment, if the line is one.
+fn version_comment(line: &str) -> Option<&str> {
+    line.trim().strip_prefix(VERSION_PREFIX).map(str::trim)
+}
+
+
+impl ZiData {
+
+    /// Reads and parses a `tzdata.zi` file from the filesystem.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ZiData> {
+        use std::fs::File;
+        use std::io::{Read, BufReader};
+
+        let f = File::open(path)?;
+        let mut r = BufReader::new(f);
+        let mut contents = String::new();
+
+        r.read_to_string(&mut contents)?;
+        parse(&contents)
+    }
+
+    /// Returns the names of every zone and link in this data, in order.
+    pub fn zone_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.zones.keys()
+                                             .chain(self.links.keys())
+                                             .map(|s| &**s)
+                                             .collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the name of the zone that the given name refers to, following
+    /// any links, or `None` if there is no such zone.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        let mut name = name;
+
+        // Links can point to other links, but shouldn’t form a cycle. Limit
+        // the number of links followed in case they do.
+        for _ in 0 ..= self.links.len() {
+            if self.zones.contains_key(name) {
+                return Some(name);
+            }
+
+            name = self.links.get(name)?;
+        }
+
+        None
+    }
+
+    /// Compiles the zone with the given name (following any links) into a
+    /// timezone data structure with that name.
+    ///
+    /// Transitions are generated for every year up to and including 2037,
+    /// the last year that fits in a compiled file's 32-bit data. After
+    /// that, the footer takes over, if the zone's last line can be
+    /// described by a POSIX TZ string.
+    pub fn tz_data(&self, name: &str) -> Result<TZData> {
+        let zone_name = self.resolve(name)
+                            .ok_or_else(|| Error::UnknownZone(name.to_owned()))?;
+
+        let mut data = self.compile(&self.zones[zone_name])?;
+        data.set_name(name);
+        Ok(data)
+    }
+
+    /// Runs through each line of a zone, working out which timespans are in
+    /// effect and when each one starts.
+    fn compile(&self, lines: &[ZoneLine]) -> Result<TZData> {
+        let mut first: Option<LocalTimeType> = None;
+        let mut rest: Vec<(i64, LocalTimeType)> = Vec::new();
+
+        // The UT instant that the current line starts at, which is only
+        // `None` for the first line.
+        let mut start: Option<i64> = None;
+
+        for line in lines {
+            let mut changes = Vec::new();
+
+            let (mut save, initial) = match line.rules {
+                ZoneRules::None => {
+                    (0, timespan(line, 0, false, ""))
+                },
+                ZoneRules::Save(save) => {
+                    (save, timespan(line, save, save != 0, ""))
+                },
+                ZoneRules::Named(ref rules_name) => {
+                    let rules = self.rules.get(rules_name)
+                                          .ok_or_else(|| Error::UnknownRules(rules_name.clone()))?;
+
+                    let last_year = line.until.as_ref().map_or(LAST_YEAR, |u| u.year + 1);
+
+                    // Before any of the rules have applied, standard time is
+                    // in effect, named using the letters of the first rule
+                    // that switches back to it.
+                    let letters = rules.iter().find(|r| r.save == 0).map_or("", |r| &*r.letters);
+                    let mut save = 0;
+                    let mut initial = timespan(line, 0, false, letters);
+
+                    for (at, rule) in rule_changes(rules, line.offset, last_year) {
+                        match start {
+                            Some(start) if at <= start => {
+                                save = rule.save;
+                                initial = timespan(line, rule.save, rule.is_dst, &rule.letters);
+                            },
+                            _ => changes.push((at, rule)),
+                        }
+                    }
+
+                    (save, initial)
+                },
+            };
+
+            match start {
+                None     => first = Some(initial),
+                Some(at) => push_transition(&mut rest, first.as_ref(), at, initial),
+            }
+
+            for (at, rule) in changes {
+                if let Some(ref until) = line.until {
+                    if at >= until.to_ut(line.offset, save) {
+                        break;
+                    }
+                }
+
+                save = rule.save;
+                let span = timespan(line, rule.save, rule.is_dst, &rule.letters);
+                push_transition(&mut rest, first.as_ref(), at, span);
+            }
+
+            start = line.until.as_ref().map(|u| u.to_ut(line.offset, save));
+        }
+
+        let first = first.ok_or(Error::NoZoneLines)?;
+        let mut data = TZData::from_timespans(first, rest);
+        data.footer = lines.last().and_then(|line| self.footer(line));
+        Ok(data)
+    }
+
+    /// Works out the POSIX TZ string for a zone’s last line in the same way
+    /// as zic, from the rules that carry on indefinitely, or from the last
+    /// rule to apply if none do. Returns `None` if the line’s rules can’t
+    /// be described by one.
+    fn footer(&self, line: &ZoneLine) -> Option<String> {
+        let rules: &[Rule] = match line.rules {
+            ZoneRules::None | ZoneRules::Save(0)  => &[],
+            ZoneRules::Save(_)                    => return None,
+            ZoneRules::Named(ref name)            => self.rules.get(name)?,
+        };
+
+        // There can be at most one rule that starts daylight-saving time
+        // indefinitely, and one that ends it.
+        let mut std_rule: Option<Rule> = None;
+        let mut dst_rule: Option<Rule> = None;
+        for rule in rules.iter().filter(|r| r.to_year.is_none()) {
+            let slot = if rule.is_dst { &mut dst_rule } else { &mut std_rule };
+            if slot.replace(rule.clone()).is_some() {
+                return None;
+            }
+        }
+
+        if std_rule.is_none() && dst_rule.is_none() {
+            match latest_rule(rules.iter()) {
+
+                // A zone that ends up in daylight-saving time stays there,
+                // which a TZ string describes as it starting at the start
+                // of the year, and ending a day after the end of it.
+                Some(rule) if rule.is_dst => {
+                    let midnight = |seconds| TimeSpec { seconds, time_type: TimeType::Wall };
+                    let std_letters = latest_rule(rules.iter().filter(|r| !r.is_dst)).map_or(String::new(), |r| r.letters.clone());
+
+                    dst_rule = Some(Rule { month: 1, day: DaySpec::Ordinal(1), time: midnight(0), .. rule.clone() });
+                    std_rule = Some(Rule {
+                        month: 12, day: DaySpec::Ordinal(31), time: midnight(24 * 60 * 60 + rule.save),
+                        save: 0, is_dst: false, letters: std_letters, .. rule.clone()
+                    });
+                },
+
+                latest => std_rule = latest.cloned(),
+            }
+        }
+
+        if std_rule.is_none() && !rules.is_empty() {
+            return None;
+        }
+
+        let std_letters = std_rule.as_ref().map_or("", |r| &*r.letters);
+        let mut footer = posix::format_abbreviation(&timespan(line, 0, false, std_letters).name);
+        footer += &posix::format_time(-line.offset);
+
+        if let (Some(std_rule), Some(dst_rule)) = (std_rule, dst_rule) {
+            footer += &posix::format_abbreviation(&timespan(line, dst_rule.save, dst_rule.is_dst, &dst_rule.letters).name);
+            if dst_rule.save != 60 * 60 {
+                footer += &posix::format_time(-(line.offset + dst_rule.save));
+            }
+
+            footer += ",";
+            footer += &posix_rule_date(&dst_rule, dst_rule.save, line.offset)?;
+            footer += ",";
+            footer += &posix_rule_date(&std_rule, dst_rule.save, line.offset)?;
+        }
+
+        // Anything out of the range of a TZ string, such as a rule that
+        // changes the time a week after the day it’s on, can’t be read back.
+        posix::parse(&footer).ok()?;
+        Some(footer)
+    }
+}
+
+
+/// Returns the rule that applies last, going by the year it ends and then
+/// the day it’s on. Like zic, this doesn’t look at the weekday, and takes
+/// the first of any that tie.
+fn latest_rule<'a, I: Iterator<Item=&'a Rule>>(rules: I) -> Option<&'a Rule> {
+    let order = |rule: &Rule| match rule.to_year {
+        None     => (i64::MAX, 0, 0),
+        Some(to) => (to, rule.month, rule.day.day_of_month(rule.month)),
+    };
+
+    rules.fold(None, |latest, rule| {
+        match latest {
+            Some(l) if order(l) >= order(rule) => Some(l),
+            _                                  => Some(rule),
+        }
+    })
+}
+
+/// Formats the day and time that a rule takes effect as one end of the
+/// rule in a TZ string, given the daylight-saving time that the rule’s
+/// zone saves, and its standard offset. TZ strings give times in the local
+/// time in effect beforehand, so these get converted from the rule’s own
+/// clock.
+fn posix_rule_date(rule: &Rule, save: i64, std_offset: i64) -> Option<String> {
+    let mut time = rule.time.seconds;
+    let month = i64::from(rule.month);
+
+    let date = match rule.day {
+        DaySpec::Ordinal(29) if rule.month == 2 => return None,
+        DaySpec::OnOrAfter(_, 0) => return None,
+
+        // Days before March are counted from zero, which needs no prefix,
+        // but later ones skip 29 February by counting from J1.
+        DaySpec::Ordinal(day) => {
+            let days_before: i64 = (1 .. rule.month).map(|m| i64::from(days_in_month(1970, m))).sum();
+            if month <= 2 { format!("{}", days_before + i64::from(day) - 1) }
+                     else { format!("J{}", days_before + i64::from(day)) }
+        },
+
+        DaySpec::Last(weekday) => format!("M{}.5.{}", month, weekday),
+
+        // Weeks in a TZ string start on the 1st, 8th, and so on, so days
+        // such as `Sun>=9` need the weekday before, a day later.
+        DaySpec::OnOrAfter(weekday, day) => {
+            let shift = i64::from(day - 1) % 7;
+            time += shift * 24 * 60 * 60;
+            format!("M{}.{}.{}", month, 1 + (day - 1) / 7, (i64::from(weekday) - shift).rem_euclid(7))
+        },
+
+        DaySpec::OnOrBefore(weekday, day) if day == days_in_month(2000, rule.month) => {
+            format!("M{}.5.{}", month, weekday)
+        },
+
+        DaySpec::OnOrBefore(weekday, day) => {
+            let shift = i64::from(day) % 7;
+            time += shift * 24 * 60 * 60;
+            format!("M{}.{}.{}", month, day / 7, (i64::from(weekday) - shift).rem_euclid(7))
+        },
+    };
+
+    if rule.time.time_type == TimeType::UTC {
+        time += std_offset;
+    }
+
+    if rule.time.time_type != TimeType::Wall && !rule.is_dst {
+        time += save;
+    }
+
+    if time == 2 * 60 * 60 {
+        Some(date)
+    }
+    else {
+        Some(format!("{}/{}", date, posix::format_time(time)))
+    }
+}
+
+
+/// Adds a transition to the list, unless it would not actually change
+/// anything from the timespan currently in effect.
+///
+/// Like `zic`, when a transition would happen at a wall-clock time no later
+/// than the one before it -- such as when a zone line and a rule both
+/// change the time at once -- the two get merged into one.
+fn push_transition(rest: &mut Vec<(i64, LocalTimeType)>, first: Option<&LocalTimeType>, at: i64, span: LocalTimeType) {
+    if let Some(&(last_at, ref last)) = rest.last() {
+        let before_last = if rest.len() >= 2 { Some(&rest[rest.len() - 2].1) } else { first };
+
+        if before_last.map_or(false, |b| at + last.offset <= last_at + b.offset) {
+            if before_last == Some(&span) {
+                rest.pop();
+            }
+            else if let Some(last) = rest.last_mut() {
+                last.1 = span;
+            }
+
+            return;
+        }
+    }
+
+    let current = rest.last().map(|t| &t.1).or(first);
+    if current != Some(&span) {
+        rest.push((at, span));
+    }
+}
+
+
+/// Creates the timespan for a zone line with a particular amount of
+/// daylight-saving time in effect.
+fn timespan(line: &ZoneLine, save: i64, is_dst: bool, letters: &str) -> LocalTimeType {
+    let offset = line.offset + save;
+
+    let name = if let Some(slash) = line.format.find('/') {
+        if is_dst { line.format[slash + 1 ..].to_owned() }
+             else { line.format[.. slash].to_owned() }
+    }
+    else if line.format.contains("%z") {
+        line.format.replace("%z", &numeric_abbreviation(offset))
+    }
+    else {
+        line.format.replace("%s", letters)
+    };
+
+    LocalTimeType { name, offset, is_dst, indicators: Indicators::UNSPECIFIED }
+}
+
+
+/// Formats an offset as a numeric abbreviation, such as "+05" or "-0330",
+/// leaving out the minutes and seconds when they are zero.
+fn numeric_abbreviation(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let seconds = offset.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+    else if minutes != 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    }
+    else {
+        format!("{}{:02}", sign, hours)
+    }
+}
+
+
+/// Expands a rule set into the list of instants at which each rule takes
+/// effect, in order, up to the end of the given year.
+///
+/// Wall-clock times depend on the amount of daylight-saving time in effect
+/// before each change, so the rules are worked through in order, keeping
+/// track of it as they go.
+fn rule_changes(rules: &[Rule], std_offset: i64, last_year: i64) -> Vec<(i64, &Rule)> {
+    let mut local_changes = Vec::new();
+
+    for rule in rules {
+        let from = rule.from_year.max(FIRST_YEAR);
+        let to = rule.to_year.unwrap_or(LAST_YEAR).min(last_year);
+
+        for year in from ..= to {
+            let local = rule.day.to_days(year, rule.month) * 86400 + rule.time.seconds;
+            local_changes.push((local, rule));
+        }
+    }
+
+    local_changes.sort_by_key(|&(local, _)| local);
+
+    let mut save = 0;
+    let mut changes = Vec::with_capacity(local_changes.len());
+    for (local, rule) in local_changes {
+        changes.push((rule.time.to_ut(local, std_offset, save), rule));
+        save = rule.save;
+    }
+
+    changes
+}
+
+
+impl TimeSpec {
+
+    /// Converts a local timestamp read from this clock into UT, given the
+    /// standard offset and daylight-saving time in effect.
+    fn to_ut(self, local: i64, std_offset: i64, save: i64) -> i64 {
+        match self.time_type {
+            TimeType::Wall      => local - std_offset - save,
+            TimeType::Standard  => local - std_offset,
+            TimeType::UTC       => local,
+        }
+    }
+}
+
+impl Until {
+
+    /// Converts this instant into a UT timestamp, given the standard offset
+    /// and daylight-saving time in effect just before it.
+    fn to_ut(self, std_offset: i64, save: i64) -> i64 {
+        let local = self.day.to_days(self.year, self.month) * 86400 + self.time.seconds;
+        self.time.to_ut(local, std_offset, save)
+    }
+}
+
+impl DaySpec {
+
+    /// Returns the day of the month that this specification is given
+    /// relative to, where the last weekday counts as the last day of the
+    /// month in a leap year.
+    fn day_of_month(self, month: u8) -> u8 {
+        match self {
+            DaySpec::Ordinal(day) | DaySpec::OnOrAfter(_, day) | DaySpec::OnOrBefore(_, day) => day,
+            DaySpec::Last(_) => days_in_month(2000, month),
+        }
+    }
+
+    /// Returns the number of days since the Unix epoch of the day that this
+    /// specification refers to in the given month.
+    ///
+    /// Days such as `Sun>=29` can refer to a day in the following month.
+    pub(crate) fn to_days(self, year: i64, month: u8) -> i64 {
+        match self {
+            DaySpec::Ordinal(day) => days_from_civil(year, month, day),
+
+            DaySpec::Last(weekday) => {
+                let last = days_from_civil(year, month, days_in_month(year, month));
+                last - (weekday_of(last) - weekday as i64 + 7) % 7
+            },
+
+            DaySpec::OnOrAfter(weekday, day) => {
+                let days = days_from_civil(year, month, day);
+                days + (weekday as i64 - weekday_of(days) + 7) % 7
+            },
+
+            DaySpec::OnOrBefore(weekday, day) => {
+                let days = days_from_civil(year, month, day);
+                days - (weekday_of(days) - weekday as i64 + 7) % 7
+            },
+        }
+    }
+}
+
+
+/// Returns the number of days since the Unix epoch of a date in the
+/// proleptic Gregorian calendar, such as -25567 for 1900-01-01. Months go
+/// from 1 to 12.
+pub fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the day of the week of a number of days since the Unix epoch, as
+/// a number of days since Sunday.
+fn weekday_of(days: i64) -> i64 {
+    // The epoch was a Thursday.
+    (days + 4).rem_euclid(7)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+
+/// Whether a field is an abbreviation of a keyword. Every keyword can be
+/// abbreviated down to its first letter.
+fn keyword_matches(field: &str, keyword: &str) -> bool {
+    !field.is_empty() && keyword.starts_with(&*field.to_lowercase())
+}
+
+/// Finds the single word that a field is an abbreviation of, returning its
+/// index. Abbreviations that could stand for more than one word, such as
+/// "Ju", don’t match anything.
+fn lookup_word(field: &str, words: &[&str]) -> Option<usize> {
+    let field = field.to_lowercase();
+    let mut matches = words.iter().enumerate().filter(|&(_, w)| !field.is_empty() && w.starts_with(&*field));
+
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Some(index),
+        _                        => None,
+    }
+}
+
+const MONTHS: &[&str] = &[
+    "january", "february", "march", "april", "may", "june", "july",
+    "august", "september", "october", "november", "december",
+];
+
+const WEEKDAYS: &[&str] = &[
+    "sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday",
+];
+
+fn parse_month(field: &str) -> Option<u8> {
+    lookup_word(field, MONTHS).map(|m| m as u8 + 1)
+}
+
+fn parse_weekday(field: &str) -> Option<u8> {
+    lookup_word(field, WEEKDAYS).map(|w| w as u8)
+}
+
+/// Parses the year in a rule’s FROM or TO field, which can also be
+/// `minimum`.
+fn parse_rule_year(field: &str) -> Option<i64> {
+    if lookup_word(field, &[ "minimum", "maximum" ]) == Some(0) {
+        Some(i64::MIN)
+    }
+    else {
+        parse_year(field)
+    }
+}
+
+/// Parses a year, rejecting any that are further away than `MAX_YEAR`.
+fn parse_year(field: &str) -> Option<i64> {
+    field.parse::<i64>().ok().filter(|year| year.unsigned_abs() <= MAX_YEAR as u64)
+}
+
+fn parse_day(field: &str) -> Option<DaySpec> {
+    if let Some(weekday) = field.strip_prefix("last") {
+        Some(DaySpec::Last(parse_weekday(weekday)?))
+    }
+    else if let Some(pos) = field.find(">=") {
+        Some(DaySpec::OnOrAfter(parse_weekday(&field[.. pos])?, field[pos + 2 ..].parse().ok()?))
+    }
+    else if let Some(pos) = field.find("<=") {
+        Some(DaySpec::OnOrBefore(parse_weekday(&field[.. pos])?, field[pos + 2 ..].parse().ok()?))
+    }
+    else {
+        Some(DaySpec::Ordinal(field.parse().ok()?))
+    }
+}
+
+/// Parses a number of hours, minutes, and seconds, such as "2", "-0:30", or
+/// "1:23:45". Fields after the hours can be written without leading zeroes.
+fn parse_hms(field: &str) -> Option<i64> {
+    let (sign, field) = match field.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None       => (1, field),
+    };
+
+    if field.is_empty() {
+        return None;
+    }
+
+    let mut seconds: i64 = 0;
+    let mut components = 0;
+    for (index, part) in field.split(':').enumerate() {
+        if index > 2 || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+        components += 1;
+    }
+
+    // A time given as "2" or "2:30" still needs to be scaled up to seconds.
+    for _ in components .. 3 {
+        seconds = seconds.checked_mul(60)?;
+    }
+
+    if seconds > MAX_HOURS * 60 * 60 {
+        return None;
+    }
+
+    Some(sign * seconds)
+}
+
+/// Parses a time of day with an optional suffix giving the clock it should
+/// be read from.
+fn parse_time(field: &str) -> Option<TimeSpec> {
+    let (time, time_type) = match field.as_bytes().last() {
+        Some(b'w')                => (&field[.. field.len() - 1], TimeType::Wall),
+        Some(b's')                => (&field[.. field.len() - 1], TimeType::Standard),
+        Some(b'u' | b'g' | b'z')  => (&field[.. field.len() - 1], TimeType::UTC),
+        _                         => (field, TimeType::Wall),
+    };
+
+    // A lone "-" means midnight.
+    let seconds = if time == "-" { 0 } else { parse_hms(time)? };
+    Some(TimeSpec { seconds, time_type })
+}
+
+/// Parses the fields of a rule line, after the `Rule` keyword and name.
+fn parse_rule(fields: &[&str]) -> Option<Rule> {
+    let from_year = parse_rule_year(fields[0])?;
+    let to_year = match lookup_word(fields[1], &[ "only", "maximum" ]) {
+        Some(0) => Some(from_year),
+        Some(_) => None,
+        None    => Some(parse_rule_year(fields[1])?),
+    };
+
+    // The SAVE field can end with ‘s’ or ‘d’ to say whether the time counts
+    // as standard or daylight-saving time, regardless of its amount.
+    let (save, is_dst) = match fields[6].as_bytes().last() {
+        Some(b's')  => (parse_hms(&fields[6][.. fields[6].len() - 1])?, Some(false)),
+        Some(b'd')  => (parse_hms(&fields[6][.. fields[6].len() - 1])?, Some(true)),
+        _           => (parse_hms(fields[6])?, None),
+    };
+
+    Some(Rule {
+        from_year,
+        to_year,
+        month:    parse_month(fields[3])?,
+        day:      parse_day(fields[4])?,
+        time:     parse_time(fields[5])?,
+        save,
+        is_dst:   is_dst.unwrap_or(save != 0),
+        letters:  if fields[7] == "-" { String::new() } else { fields[7].to_owned() },
+    })
+}
+
+/// Parses the fields of a zone line, after the `Zone` keyword and name if
+/// it isn’t a continuation line.
+fn parse_zone_line(fields: &[&str]) -> Option<ZoneLine> {
+    if fields.len() < 3 || fields.len() > 7 {
+        return None;
+    }
+
+    let rules = if fields[1] == "-" {
+        ZoneRules::None
+    }
+    else if let Some(save) = parse_hms(fields[1]) {
+        ZoneRules::Save(save)
+    }
+    else {
+        ZoneRules::Named(fields[1].to_owned())
+    };
+
+    let until = if fields.len() > 3 {
+        Some(Until {
+            year:   parse_year(fields[3])?,
+            month:  fields.get(4).map_or(Some(1), |f| parse_month(f))?,
+            day:    fields.get(5).map_or(Some(DaySpec::Ordinal(1)), |f| parse_day(f))?,
+            time:   fields.get(6).map_or(Some(TimeSpec { seconds: 0, time_type: TimeType::Wall }), |f| parse_time(f))?,
+        })
+    }
+    else {
+        None
+    };
+
+    Some(ZoneLine {
+        offset:  parse_hms(fields[0])?,
+        rules,
+        format:  fields[2].to_owned(),
+        until,
+    })
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Error {
+
+    /// The error when a line couldn’t be parsed.
+    InvalidLine {
+
+        /// The number of the line that couldn’t be parsed, starting at 1.
+        line_number: usize,
+    },
+
+    /// The error when two zones are given the same name.
+    DuplicateZone(String),
+
+    /// The error when the last line of a zone has an UNTIL field, but there
+    /// is no continuation line after it.
+    MissingContinuation(String),
+
+    /// The error when there is no zone or link with the requested name.
+    UnknownZone(String),
+
+    /// The error when a zone line refers to a rule set that doesn’t exist.
+    UnknownRules(String),
+
+    /// The error when a zone has no lines to compile.
+    NoZoneLines,
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InvalidLine { .. }       => "invalid line",
+            Error::DuplicateZone(_)         => "duplicate zone",
+            Error::MissingContinuation(_)   => "missing continuation line",
+            Error::UnknownZone(_)           => "unknown zone",
+            Error::UnknownRules(_)          => "unknown rules",
+            Error::NoZoneLines              => "no zone lines",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidLine { line_number }     => write!(f, "invalid line (line {})", line_number),
+            Error::DuplicateZone(ref name)         => write!(f, "zone {:?} is defined twice", name),
+            Error::MissingContinuation(ref name)   => write!(f, "zone {:?} is missing a continuation line", name),
+            Error::UnknownZone(ref name)           => write!(f, "unknown zone {:?}", name),
+            Error::UnknownRules(ref name)          => write!(f, "unknown rules {:?}", name),
+            Error::NoZoneLines                     => write!(f, "zone has no lines"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# version 2024a
+# This zic input file is in the public domain.
+R NY 1920 1921 - Mar lastSu 2 1 D
+R NY 1920 o - O lastSu 2 0 S
+R NY 1921 1966 - S lastSu 2 0 S
+R u 1967 2006 - O lastSu 2 0 S
+R u 1987 2006 - Ap Su>=1 2 1 D
+R u 2007 ma - Mar Su>=8 2 1 D
+R u 2007 ma - N Su>=1 2 0 S
+Z America/New_York -4:56:2 - LMT 1883 N 18 17u
+-5 NY E%sT 1967
+-5 u E%sT
+Z Etc/UTC 0 - UTC
+L America/New_York US/Eastern
+L Etc/UTC Etc/Universal
+L Etc/Universal Zulu
+";
+
+    #[test]
+    fn version_header() {
+        assert_eq!(read_version(SAMPLE.as_bytes()).unwrap(), Some("2024a".to_owned()));
+        assert_eq!(read_version(&b"# This zic input file is in the public domain.\n# version 2024b\n"[..]).unwrap(), Some("2024b".to_owned()));
+        assert_eq!(read_version(&b"R d 1916 o - Jun 14 23s 1 S\n# version 2024b\n"[..]).unwrap(), None);
+        assert_eq!(read_version(&b""[..]).unwrap(), None);
+
+        assert_eq!(parse("# Header\n# version 2024b\nR d 1916 o - Jun 14 23s 1 S\n").unwrap().version, Some("2024b".to_owned()));
+        assert_eq!(parse("R d 1916 o - Jun 14 23s 1 S\n# version 2024b\n").unwrap().version, None);
+    }
+
+    #[test]
+    fn parses_lines() {
+        let data = parse(SAMPLE).unwrap();
+        assert_eq!(data.version, Some("2024a".to_owned()));
+        assert_eq!(data.rules["u"].len(), 4);
+        assert_eq!(data.zones["America/New_York"].len(), 3);
+
+        assert_eq!(data.rules["u"][2], Rule {
+            from_year: 2007,
+            to_year: None,
+            month: 3,
+            day: DaySpec::OnOrAfter(0, 8),
+            time: TimeSpec { seconds: 7200, time_type: TimeType::Wall },
+            save: 3600,
+            is_dst: true,
+            letters: "D".to_owned(),
+        });
+
+        assert_eq!(data.zones["America/New_York"][0], ZoneLine {
+            offset: -17762,
+            rules: ZoneRules::None,
+            format: "LMT".to_owned(),
+            until: Some(Until {
+                year: 1883,
+                month: 11,
+                day: DaySpec::Ordinal(18),
+                time: TimeSpec { seconds: 61200, time_type: TimeType::UTC },
+            }),
+        });
+    }
+
+    #[test]
+    fn follows_links() {
+        let data = parse(SAMPLE).unwrap();
+        assert_eq!(data.resolve("Zulu"), Some("Etc/UTC"));
+        assert_eq!(data.resolve("Etc/UTC"), Some("Etc/UTC"));
+        assert_eq!(data.resolve("Mars/Olympus_Mons"), None);
+        assert_eq!(data.zone_names().len(), 5);
+    }
+
+    #[test]
+    fn compiles_zone() {
+        let data = parse(SAMPLE).unwrap();
+        let tz = data.tz_data("US/Eastern").unwrap();
+        let at = |timestamp| tz.local_time_type_at(timestamp);
+
+        assert_eq!(tz.name, Some("US/Eastern".to_owned()));
+        assert_eq!(tz.local_time_types[0].name, "LMT");
+        assert_eq!(tz.transitions[0].timestamp, -2_717_650_800);
+        assert_eq!(at(-2_717_650_800).name, "EST");
+
+        // 2024-03-10 07:00 UTC, and 2024-11-03 06:00 UTC
+        assert_eq!((at(1_710_053_999).offset, at(1_710_054_000).offset), (-18000, -14400));
+        assert_eq!((at(1_730_613_599).offset, at(1_730_613_600).offset), (-14400, -18000));
+        assert!(at(1_710_054_000).is_dst);
+        assert_eq!(tz.transitions.last().unwrap().timestamp, 2_140_668_000);
+    }
+
+    #[test]
+    fn footers() {
+        let data = parse(SAMPLE).unwrap();
+        assert_eq!(data.tz_data("US/Eastern").unwrap().footer, Some("EST5EDT,M3.2.0,M11.1.0".to_owned()));
+
+        // The expected footers come from zic.
+        let data = parse("\
+R P 2000 ma - Mar Sun>=9 2 1 D
+R P 2000 ma - N Sun<=25 2s 0 S
+R Q 1990 2000 - Ap 1 0 1 D
+R Q 1990 1999 - O 1 0 0 S
+R W 2000 ma - Mar lastSu 1u 0 -
+R W 2000 ma - O lastSu 1u -1 -
+Z T/Shifted -5 P E%sT
+Z T/Perpetual -5 Q E%sT
+Z T/Negative 1 W IST/GMT
+Z T/Fixed 5:30 - +0530
+Z T/Saving -5 1 EDT
+").unwrap();
+
+        let footer = |name| data.tz_data(name).unwrap().footer;
+        assert_eq!(footer("T/Shifted"), Some("EST5EDT,M3.2.6/26,M11.3.3/99".to_owned()));
+        assert_eq!(footer("T/Perpetual"), Some("EST5EDT,0/0,J365/25".to_owned()));
+        assert_eq!(footer("T/Negative"), Some("IST-1GMT0,M10.5.0,M3.5.0/1".to_owned()));
+        assert_eq!(footer("T/Fixed"), Some("<+0530>-5:30".to_owned()));
+        assert_eq!(footer("T/Saving"), None);
+    }
+
+    #[test]
+    fn out_of_range() {
+        for input in &[
+            "Z X 0 - X 9223372036854775807\n1 - Y\n",
+            "Z X 0 - X -9223372036854775808\n1 - Y\n",
+            "Z X 0 - X minimum\n1 - Y\n",
+            "Z X 0 - X 1000001\n1 - Y\n",
+            "Z X 2562047788015215:30:08 - X\n",
+            "Z X 0:9223372036854775807 - X\n",
+            "Z X 1000001 - X\n",
+            "R r 2000 9223372036854775807 - Mar lastSu 2 1 D\n",
+            "R r 2000 o - Mar lastSu 1000001 1 D\n",
+        ] {
+            assert!(parse(input).is_err(), "{:?}", input);
+        }
+
+        let data = parse("R r minimum maximum - Mar lastSu 2 1 D\nZ X 0 - X -1000000\n1 r X%s 1000000\n2 - Y\n").unwrap();
+        let tz = data.tz_data("X").unwrap();
+        assert_eq!(tz.local_time_type_at(0).name, "XD");
+        assert_eq!(tz.local_time_type_at(i64::MAX).name, "Y");
+    }
+
+    #[test]
+    fn numeric_abbreviations() {
+        assert_eq!(numeric_abbreviation(19800), "+0530");
+        assert_eq!(numeric_abbreviation(-10800), "-03");
+        assert_eq!(numeric_abbreviation(0), "+00");
+    }
+}
@@ -0,0 +1,146 @@
+//! Checks every compiled zone in the system’s zoneinfo tree against the C
+//! library, by comparing the offset and abbreviation that `localtime_r`
+//! gives with `TZ` set to the file against what we get from parsing it.
+//!
+//! The test passes without checking anything if there’s no tree to read.
+//! Everything happens in one test function, because setting `TZ` affects
+//! the whole process.
+
+#![cfg(unix)]
+
+extern crate libc;
+extern crate zoneinfo_compiled;
+
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use zoneinfo_compiled::{posix, TZData};
+use zoneinfo_compiled::parser::{self, Limits};
+
+
+/// Where to find the tree, unless `ZONEINFO` says otherwise.
+const DEFAULT_ZONEINFO: &str = "/usr/share/zoneinfo";
+
+/// Instants to check in every zone, on top of the ones around its
+/// transitions: roughly the start and middle of every fifth year from 1900
+/// to 2100, so both summer and winter get checked.
+fn sample_instants() -> Vec<i64> {
+    (0 ..= 80).map(|i| -2_208_988_800 + i * 5 * 31_556_952 / 2).collect()
+}
+
+extern "C" {
+    fn tzset();
+}
+
+
+#[test]
+fn system_zoneinfo() {
+    let root = env::var_os("ZONEINFO").filter(|r| !r.is_empty()).map_or_else(|| PathBuf::from(DEFAULT_ZONEINFO), PathBuf::from);
+    if !root.is_dir() {
+        println!("skipping: {} is not a directory", root.display());
+        return;
+    }
+
+    let mut files = Vec::new();
+    find_compiled_files(&root, &mut files);
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let contents = fs::read(path).unwrap();
+        let raw = match parser::parse(contents, Limits::sensible()) {
+            Ok(raw) => raw,
+            Err(e)  => { failures.push(format!("{}: {}", path.display(), e)); continue },
+        };
+
+        let tz = match zoneinfo_compiled::cook(raw) {
+            Ok(tz) => tz,
+            Err(e) => { failures.push(format!("{}: {}", path.display(), e)); continue },
+        };
+
+        let footer = match tz.footer.as_ref().map(|f| posix::parse(f)) {
+            Some(Ok(footer)) => Some(footer),
+            Some(Err(e))     => { failures.push(format!("{}: {}", path.display(), e)); continue },
+            None             => None,
+        };
+
+        set_tz(path);
+
+        let mut instants = sample_instants();
+        for t in &tz.transitions {
+            instants.push(t.timestamp.saturating_sub(1));
+            instants.push(t.timestamp);
+        }
+
+        for instant in instants {
+            if disagrees_with_glibc(&tz, footer.is_some(), instant) {
+                continue;
+            }
+
+            let expected = match localtime(instant) {
+                Some(expected) => expected,
+                None => continue,
+            };
+
+            let ltt = tz.local_time_type_at(instant);
+            if (ltt.offset, &*ltt.name) != (expected.0, &*expected.1) {
+                failures.push(format!("{} at {}: {} ({}), but libc says {} ({})",
+                                      path.display(), instant, ltt.name, ltt.offset, expected.1, expected.0));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} of {} files disagree:\n{}", failures.len(), files.len(), failures.join("\n"));
+}
+
+/// Returns whether this is an instant where glibc knowingly does something
+/// other than what RFC 8536 says: it uses the footer from the last
+/// transition onwards, rather than after it, and before the first
+/// transition it uses the first standard time type, rather than type 0.
+fn disagrees_with_glibc(tz: &TZData, has_footer: bool, instant: i64) -> bool {
+    match (tz.transitions.first(), tz.transitions.last()) {
+        (Some(first), _) if instant < first.timestamp => tz.local_time_types[0].is_dst,
+        (_, Some(last))  if instant == last.timestamp => has_footer,
+        _                                             => false,
+    }
+}
+
+/// Collects the path of every file under `dir` that starts with the TZif
+/// magic, skipping everything else that lives in a zoneinfo tree.
+fn find_compiled_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_compiled_files(&path, files);
+        }
+        else if fs::read(&path).ok().map_or(false, |c| c.starts_with(b"TZif")) {
+            files.push(path);
+        }
+    }
+}
+
+fn set_tz(path: &Path) {
+    env::set_var("TZ", format!(":{}", path.display()));
+    unsafe { tzset() };
+}
+
+/// Returns the offset and abbreviation that the C library gives for an
+/// instant, or `None` if it can’t represent it.
+fn localtime(instant: i64) -> Option<(i64, String)> {
+    if instant < i64::from(i32::MIN) * 400 || instant > i64::from(i32::MAX) * 400 {
+        return None;
+    }
+
+    let timer = instant as libc::time_t;
+    let mut tm: libc::tm = unsafe { mem::zeroed() };
+    if unsafe { libc::localtime_r(&timer, &mut tm) }.is_null() || tm.tm_zone.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(tm.tm_zone) }.to_string_lossy().into_owned();
+    Some((tm.tm_gmtoff as i64, name))
+}
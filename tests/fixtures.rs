@@ -0,0 +1,84 @@
+//! Parses and cooks every compiled file in `tests/fixtures`, and compares
+//! the result with the text file next to it.
+//!
+//! The compiled files come from `generate.sh`. Most are zic’s own output,
+//! but `london-v1.tzif` and `right-utc-v4.tzif` are synthetic, made by
+//! patching the version and contents of files from an older zic, as the
+//! script explains.
+//!
+//! To rewrite the expected output after a deliberate change, run this test
+//! with `UPDATE_FIXTURES` set, and check the differences before committing
+//! them.
+
+extern crate zoneinfo_compiled;
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use zoneinfo_compiled::parser::{self, Limits};
+
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut paths: Vec<_> = fs::read_dir(dir).unwrap()
+                               .map(|entry| entry.unwrap().path())
+                               .filter(|path| path.extension().map_or(false, |e| e == "tzif"))
+                               .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    paths
+}
+
+/// Describes the parsed and cooked contents of a compiled file, one line
+/// per local time type, transition, and leap second.
+fn describe(contents: Vec<u8>) -> String {
+    let raw = parser::parse(contents, Limits::sensible()).unwrap();
+    let version = raw.header.version;
+    let tz = zoneinfo_compiled::cook(raw).unwrap();
+
+    let mut out = String::new();
+    writeln!(out, "version: {}", if version == 0 { '1' } else { char::from(version) }).unwrap();
+
+    for (i, ltt) in tz.local_time_types.iter().enumerate() {
+        writeln!(out, "type {}: {} {} dst:{} std:{:?} ut:{:?}",
+                 i, ltt.name, ltt.offset, ltt.is_dst, ltt.indicators.is_std, ltt.indicators.is_ut).unwrap();
+    }
+
+    for t in &tz.transitions {
+        writeln!(out, "transition {}: type {}", t.timestamp, t.local_time_type).unwrap();
+    }
+
+    for ls in &tz.leap_seconds {
+        writeln!(out, "leap second {}: {}", ls.timestamp, ls.leap_second_count).unwrap();
+    }
+
+    match tz.footer {
+        Some(footer) => writeln!(out, "footer: {}", footer).unwrap(),
+        None         => writeln!(out, "no footer").unwrap(),
+    }
+
+    out
+}
+
+
+#[test]
+fn fixtures_match() {
+    let update = env::var_os("UPDATE_FIXTURES").is_some();
+    let mut mismatches = Vec::new();
+
+    for path in fixtures() {
+        let actual = describe(fs::read(&path).unwrap());
+        let expected_path = path.with_extension("txt");
+
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+        }
+        else if fs::read_to_string(&expected_path).ok().as_deref() != Some(&*actual) {
+            mismatches.push(path.file_name().unwrap().to_string_lossy().into_owned());
+        }
+    }
+
+    assert!(mismatches.is_empty(), "cooked output differs for: {}", mismatches.join(", "));
+}
@@ -0,0 +1,54 @@
+#!/bin/sh
+# Regenerates the compiled fixtures from the system’s tzdata.zi and from
+# fixtures.zi, using zic.  Run the fixtures test with UPDATE_FIXTURES=1
+# afterwards to rewrite the expected output, and check the differences.
+set -eu
+
+cd "$(dirname "$0")"
+TZDATA=${TZDATA:-/usr/share/zoneinfo/tzdata.zi}
+LEAPSECONDS=${LEAPSECONDS:-/usr/share/zoneinfo/leapseconds}
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+zic -b fat  -d "$work/fat"  "$TZDATA"
+zic -b slim -d "$work/slim" "$TZDATA"
+zic -b fat  -d "$work/right" -L "$LEAPSECONDS" "$TZDATA"
+
+zic -b slim -d "$work/truncated" -L "$LEAPSECONDS" -r @1000000000 "$TZDATA"
+
+zic -b fat -d "$work/custom" fixtures.zi
+
+cp "$work/fat/Europe/London"        london-fat.tzif
+cp "$work/slim/Europe/London"       london-slim.tzif
+cp "$work/slim/America/Nuuk"        nuuk-v3.tzif
+cp "$work/right/Europe/London"      right-london.tzif
+cp "$work/custom/Fixture/NoTransitions" no-transitions.tzif
+cp "$work/custom/Fixture/Unspecified"   unspecified.tzif
+cp "$work/custom/Fixture/Extreme"       extreme.tzif
+
+# The last two fixtures are synthetic: rather than coming straight out of
+# zic, they get made by patching its output, so they’re only as faithful as
+# the patching below.
+#
+# zic no longer writes version 1 files, so make one by keeping only the
+# 32-bit block of a fat file, and setting the version byte to zero.
+python3 - "$work/fat/Europe/London" london-v1.tzif <<'PYTHON'
+import struct, sys
+data = open(sys.argv[1], 'rb').read()
+isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt = struct.unpack('>6l', data[20:44])
+length = 44 + timecnt * 5 + typecnt * 6 + charcnt + leapcnt * 8 + isstdcnt + isutcnt
+open(sys.argv[2], 'wb').write(data[:4] + b'\0' + data[5:length])
+PYTHON
+
+# Truncating the start of the leap second table is what version 4 is for,
+# but versions of zic before 2021b still label the result as version 2, so
+# relabel it. This hasn’t been checked against the output of a newer zic,
+# which is what should replace it once one is at hand.
+python3 - "$work/truncated/Etc/UTC" right-utc-v4.tzif <<'PYTHON'
+import struct, sys
+data = bytearray(open(sys.argv[1], 'rb').read())
+isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt = struct.unpack('>6l', data[20:44])
+length = 44 + timecnt * 5 + typecnt * 6 + charcnt + leapcnt * 8 + isstdcnt + isutcnt
+data[4] = data[length + 4] = ord('4')
+open(sys.argv[2], 'wb').write(data)
+PYTHON
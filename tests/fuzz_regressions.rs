@@ -0,0 +1,79 @@
+//! Runs every input in `fuzz/regressions` through the same code as the
+//! fuzz target it was found with, to make sure none of them panic.
+//!
+//! Each directory holds the crashes found by one target, named `crash-*`,
+//! along with a small corpus of inputs that cover most of its code.
+
+extern crate zoneinfo_compiled;
+#[cfg(feature = "datetime")] extern crate datetime;
+
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use zoneinfo_compiled::parser::{self, Limits};
+
+
+/// 2000 to 2040, which is short enough to extrapolate a footer over.
+const RANGE: Range<i64> = 946_684_800 .. 2_208_988_800;
+
+
+fn inputs(target: &str) -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/regressions").join(target);
+    let mut inputs: Vec<_> = fs::read_dir(dir).unwrap()
+                                .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+                                .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty());
+    inputs
+}
+
+#[test]
+fn parse() {
+    for data in inputs("parse") {
+        if let Ok(mut tz) = parser::parse(data, Limits::sensible()) {
+            let _ = tz.compact();
+            zoneinfo_compiled::writer::write(&tz);
+        }
+    }
+}
+
+#[test]
+fn cook() {
+    for data in inputs("cook") {
+        let tz = match parser::parse(data, Limits::sensible()).and_then(zoneinfo_compiled::cook) {
+            Ok(tz)  => tz,
+            Err(_)  => continue,
+        };
+
+        for &timestamp in &[ i64::MIN, -1, 0, 1_000_000_000, i64::MAX ] {
+            tz.local_time_type_at(timestamp);
+            tz.resolve_local(timestamp);
+        }
+
+        for t in &tz.transitions {
+            tz.resolve_local(t.timestamp);
+        }
+
+        let _ = tz.equivalent(&tz, RANGE);
+
+        let mut normalized = tz.clone();
+        normalized.normalize();
+
+        let mut truncated = tz.clone();
+        let _ = truncated.truncate(RANGE.start, RANGE.end);
+
+        let _ = zoneinfo_compiled::writer::to_bytes(&tz);
+    }
+}
+
+#[cfg(feature = "datetime")]
+#[test]
+fn compiled_data() {
+    use datetime::zone::TimeZone;
+    use zoneinfo_compiled::CompiledData;
+
+    for data in inputs("compiled_data") {
+        let _ = TimeZone::parse(data);
+    }
+}
@@ -0,0 +1,163 @@
+//! Property tests over randomly-generated zones.
+//!
+//! The generator produces the internal structure of a valid zoneinfo file,
+//! which gets written out as bytes and read back in, checking that nothing
+//! gets lost along the way and that lookups agree with the transitions.
+
+extern crate proptest;
+extern crate zoneinfo_compiled;
+
+use proptest::prelude::*;
+
+use zoneinfo_compiled::parser::{self, Header, LeapSecondData, Limits, LocalTimeTypeData, TransitionData};
+use zoneinfo_compiled::{posix, writer, LocalTimeType};
+
+
+/// Abbreviations to pick from, some of which share their endings.
+const ABBREVIATIONS: &[&str] = &[ "UTC", "GMT", "BST", "CET", "CEST", "EST", "EDT", "-00", "+0530" ];
+
+/// Footers to pick from, all of which can be parsed.
+const FOOTERS: &[&str] = &[ "UTC0", "CET-1CEST,M3.5.0,M10.5.0/3", "<+0530>-5:30", "EST5EDT,0/0,J365/25" ];
+
+
+/// A local time type, with the index of its abbreviation and its two
+/// indicators.
+fn local_time_type() -> impl Strategy<Value = (i32, bool, usize, bool, bool)> {
+    (-93_599 .. 93_600i32, any::<bool>(), 0 .. ABBREVIATIONS.len(), any::<bool>(), any::<bool>())
+}
+
+prop_compose! {
+    fn tz_data()(types in prop::collection::vec(local_time_type(), 1 .. 12),
+                 timestamps in prop::collection::btree_set(any::<i64>(), 0 .. 40),
+                 indices in prop::collection::vec(any::<prop::sample::Index>(), 40),
+                 leap_seconds in prop::collection::btree_set(-1_000_000_000 .. 4_000_000_000i64, 0 .. 5),
+                 has_flags in any::<bool>(),
+                 footer in prop::option::of(prop::sample::select(FOOTERS)),
+                 version in prop::sample::select(vec![ b'2', b'3', b'4' ]))
+                -> parser::TZData
+    {
+        let mut strings: Vec<u8> = Vec::new();
+        let time_info: Vec<_> = types.iter().map(|&(offset, is_dst, abbreviation, _, _)| {
+            let name = ABBREVIATIONS[abbreviation].as_bytes();
+            let name_offset = match strings.split(|&c| c == 0).position(|s| s == name) {
+                Some(_) => strings.windows(name.len() + 1).position(|w| &w[.. name.len()] == name && w[name.len()] == 0).unwrap(),
+                None    => { strings.extend_from_slice(name); strings.push(0); strings.len() - name.len() - 1 },
+            };
+
+            LocalTimeTypeData { offset, is_dst: is_dst as u8, name_offset: name_offset as u8 }
+        }).collect();
+
+        let transitions: Vec<_> = timestamps.into_iter().zip(&indices).map(|(timestamp, index)| {
+            TransitionData { timestamp, local_time_type_index: index.index(types.len()) as u8 }
+        }).collect();
+
+        let leap_seconds: Vec<_> = leap_seconds.into_iter().enumerate().map(|(i, timestamp)| {
+            LeapSecondData { timestamp, leap_second_count: i as i32 + 1 }
+        }).collect();
+
+        let standard_flags: Vec<u8> = if has_flags { types.iter().map(|t| t.3 as u8).collect() } else { Vec::new() };
+        let gmt_flags: Vec<u8> = if has_flags { types.iter().map(|t| t.4 as u8).collect() } else { Vec::new() };
+
+        parser::TZData {
+            header: Header {
+                version,
+                num_gmt_flags:         gmt_flags.len() as u32,
+                num_standard_flags:    standard_flags.len() as u32,
+                num_leap_seconds:      leap_seconds.len() as u32,
+                num_transitions:       transitions.len() as u32,
+                num_local_time_types:  time_info.len() as u32,
+                num_abbr_chars:        strings.len() as u32,
+            },
+            transitions,
+            time_info,
+            leap_seconds,
+            strings,
+            standard_flags,
+            gmt_flags,
+            footer: footer.map(str::to_owned),
+        }
+    }
+}
+
+/// Whether two local time types have the same abbreviation, offset, and DST
+/// flag, as the ones from a footer have no indicators.
+fn same_type(a: &LocalTimeType, b: &LocalTimeType) -> bool {
+    (&a.name, a.offset, a.is_dst) == (&b.name, b.offset, b.is_dst)
+}
+
+
+proptest! {
+    #[test]
+    fn parse_never_panics(bytes in prop::collection::vec(any::<u8>(), 0 .. 512)) {
+        let _ = parser::parse(bytes.clone(), Limits::sensible());
+        let _ = zoneinfo_compiled::parse(bytes);
+    }
+
+    #[test]
+    fn parse_never_panics_on_mangled_files(raw in tz_data(), position in any::<prop::sample::Index>(), byte in any::<u8>()) {
+        let mut bytes = writer::write(&raw);
+        let position = position.index(bytes.len());
+        bytes[position] = byte;
+        let _ = zoneinfo_compiled::parse(bytes);
+    }
+
+    #[test]
+    fn round_trip(raw in tz_data()) {
+        let parsed = parser::parse(writer::write(&raw), Limits::sensible()).unwrap();
+        prop_assert_eq!(parsed, raw);
+    }
+
+    #[test]
+    fn cook(raw in tz_data()) {
+        let num_local_time_types = raw.header.num_local_time_types as usize;
+        let tz = zoneinfo_compiled::cook(raw.clone()).unwrap();
+        prop_assert_eq!(tz.local_time_types.len(), num_local_time_types);
+        prop_assert_eq!(tz.transitions.len(), raw.transitions.len());
+        prop_assert!(tz.transitions.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+
+        for (i, ltt) in tz.local_time_types.iter().enumerate() {
+            prop_assert_eq!(ltt.offset, i64::from(raw.time_info[i].offset));
+            prop_assert_eq!(ltt.is_dst, raw.time_info[i].is_dst != 0);
+        }
+    }
+
+    #[test]
+    fn lookups(raw in tz_data()) {
+        let tz = zoneinfo_compiled::cook(raw).unwrap();
+        let footer = tz.footer.as_ref().map(|f| posix::parse(f).unwrap());
+
+        match tz.transitions.first() {
+            Some(first) if first.timestamp > i64::MIN => {
+                prop_assert_eq!(&*tz.local_time_type_at(first.timestamp - 1), &tz.local_time_types[0]);
+            },
+            Some(_) => {},
+            None => {
+                let expected = footer.as_ref().map_or(&tz.local_time_types[0], |f| f.local_time_type_at(0));
+                prop_assert!(same_type(&tz.local_time_type_at(0), expected));
+            },
+        }
+
+        for (i, t) in tz.transitions.iter().enumerate() {
+            let governing = &tz.local_time_types[t.local_time_type];
+            prop_assert_eq!(&*tz.local_time_type_at(t.timestamp), governing);
+
+            match (tz.transitions.get(i + 1), footer.as_ref()) {
+                (Some(next), _) => prop_assert_eq!(&*tz.local_time_type_at(next.timestamp - 1), governing),
+                (None, None)    => prop_assert_eq!(&*tz.local_time_type_at(i64::MAX), governing),
+                (None, Some(footer)) if t.timestamp < i64::MAX => {
+                    let after = t.timestamp + 1;
+                    prop_assert!(same_type(&tz.local_time_type_at(after), footer.local_time_type_at(after)));
+                },
+                (None, Some(_)) => {},
+            }
+        }
+    }
+
+    #[test]
+    fn normalize_keeps_local_times(raw in tz_data()) {
+        let tz = zoneinfo_compiled::cook(raw).unwrap();
+        let mut normalized = tz.clone();
+        normalized.normalize();
+        prop_assert!(normalized.equivalent(&tz, -2_000_000_000 .. 2_000_000_000).unwrap());
+    }
+}